@@ -0,0 +1,261 @@
+use chrono::{TimeDelta, Utc};
+use iustadji_core::clock::ClockWatch;
+use iustadji_core::control::{self, Call, Reply};
+use iustadji_core::{fetch, Action, Core, Paths};
+use iustadji_domain::NotificationData;
+use notify_rust::Notification;
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::flag;
+use signal_hook::iterator::Signals;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::{env, thread};
+use tokio_util::sync::CancellationToken;
+
+const UNIT: &str = include_str!("../systemd/iustadji.service");
+const TICK: Duration = Duration::from_secs(1);
+
+// what the loop shares with the control socket
+struct Daemon {
+    core: Mutex<Core>,
+    socket: PathBuf,
+    stop: Arc<AtomicBool>,
+    reload: Arc<AtomicBool>,
+    show_next: AtomicBool,
+    // cancelled on SIGTERM/SIGINT, so a fetch in progress doesn't hold up the stop
+    stopping: CancellationToken,
+    // the app took over the socket, it is no longer ours to remove
+    handed_over: AtomicBool,
+}
+
+impl Daemon {
+    fn handle(&self, call: Call) -> Reply {
+        let now = Utc::now();
+        match call {
+            Call::Mute { minutes } => self.core.lock().unwrap().apply_mute(minutes, now)?,
+            Call::Unmute => self.core.lock().unwrap().unmute()?,
+            Call::Status => {}
+            Call::Next => {
+                self.show_next.store(true, Ordering::Relaxed);
+                return Ok(None);
+            }
+            Call::Reload => {
+                self.reload.store(true, Ordering::Relaxed);
+                return Ok(None);
+            }
+            // the app is starting: it takes over the reminders instead of showing them twice
+            Call::Open { .. } => {
+                self.hand_over();
+                return Err("The daemon stopped and left the reminders to the app".to_string());
+            }
+        }
+        Ok(Some(self.core.lock().unwrap().status(now)))
+    }
+
+    // the socket is freed before the answer, so the app can bind it right away
+    fn hand_over(&self) {
+        println!("handing over to the app");
+        self.handed_over.store(true, Ordering::Relaxed);
+        if let Err(err) = fs::remove_file(&self.socket) {
+            eprintln!("Failed to remove {}: {}", self.socket.display(), err);
+        }
+        self.stop.store(true, Ordering::Relaxed);
+        self.stopping.cancel();
+    }
+
+    // there is no card to open without the window
+    fn on_action(&self, action: Action, notification: NotificationData) {
+        let mut core = self.core.lock().unwrap();
+        let result = match action {
+            Action::Open => Ok(()),
+            Action::Snooze => {
+                let minutes = core.settings().snooze_minutes;
+                core.snooze(notification, minutes, Utc::now());
+                Ok(())
+            }
+            Action::Skip => core.skip(&notification.id),
+            Action::Learned => core.mark_learned(&notification.id),
+        };
+        if let Err(err) = result {
+            eprintln!("{}", err);
+        }
+    }
+}
+
+// the scheduler of the app without its window: SIGHUP reloads settings, SIGTERM/SIGINT stop it,
+// the control socket takes the same commands as the one of the app. It stops as well when the
+// app starts, there is only ever one of them showing reminders
+pub fn run(paths: Paths) -> Result<(), String> {
+    let socket = paths.socket.clone();
+    let daemon = Arc::new(Daemon {
+        core: Mutex::new(Core::new(paths)?),
+        socket: socket.clone(),
+        stop: Arc::new(AtomicBool::new(false)),
+        reload: Arc::new(AtomicBool::new(false)),
+        show_next: AtomicBool::new(false),
+        stopping: CancellationToken::new(),
+        handed_over: AtomicBool::new(false),
+    });
+    let stop = &daemon.stop;
+    let mut signals = Signals::new([SIGTERM, SIGINT]).map_err(|err| err.to_string())?;
+    {
+        let stop = Arc::clone(stop);
+        let stopping = daemon.stopping.clone();
+        thread::spawn(move || {
+            if signals.forever().next().is_some() {
+                stop.store(true, Ordering::Relaxed);
+                stopping.cancel();
+            }
+        });
+    }
+    flag::register(SIGHUP, Arc::clone(&daemon.reload)).map_err(|err| err.to_string())?;
+
+    {
+        let daemon = Arc::clone(&daemon);
+        control::serve(&socket, move |call| daemon.handle(call))
+            .map_err(|err| format!("Failed to listen on {}: {}", socket.display(), err))?;
+    }
+
+    let core = &daemon.core;
+    let stopping = &daemon.stopping;
+    rebuild(core, stopping);
+    // deadlines are wall clock time, monotonic time stands still while suspended
+    let mut next_at = Utc::now();
+    let zone_now = || core.lock().unwrap().settings().zone().now();
+    let mut clock = ClockWatch::new(zone_now());
+    while !stop.load(Ordering::Relaxed) {
+        // after a suspend or a clock change the timed reminders are resolved again and the
+        // rotation waits a full interval where it left off, whatever came due meanwhile is
+        // dropped instead of shown in a burst
+        if let Some(jump) = clock.check(zone_now()) {
+            println!("clock jumped by {} min, rescheduling", jump.num_minutes());
+            core.lock().unwrap().reschedule(Utc::now());
+            let interval = core.lock().unwrap().settings().interval;
+            next_at = Utc::now() + TimeDelta::minutes(interval as i64);
+        }
+        if daemon.reload.swap(false, Ordering::Relaxed) {
+            println!("reloading settings");
+            let reloaded = core.lock().unwrap().reload_settings();
+            match reloaded {
+                Ok(()) => {
+                    rebuild(core, stopping);
+                    next_at = Utc::now();
+                }
+                Err(err) => eprintln!("{}, keeping the old settings", err),
+            }
+        }
+        if daemon.show_next.swap(false, Ordering::Relaxed) {
+            next_at = Utc::now();
+        }
+
+        let now = Utc::now();
+        let (due, interval, muted) = {
+            let mut core = core.lock().unwrap();
+            core.reload_mute();
+            let muted = core.is_muted(now);
+            (core.due_notifications(now), core.settings().interval, muted)
+        };
+        for notification in due {
+            show(&daemon, &notification);
+        }
+
+        if Utc::now() >= next_at {
+            next_at = Utc::now() + TimeDelta::minutes(interval as i64);
+            if !muted {
+                // a used up rotation starts over with a fresh queue, so relative windows follow the date
+                let mut notification = core.lock().unwrap().next_notification(now);
+                if notification.is_none() {
+                    rebuild(core, stopping);
+                    notification = core.lock().unwrap().next_notification(now);
+                }
+                if let Some(notification) = notification {
+                    show(&daemon, &notification);
+                }
+            }
+        }
+        thread::sleep(TICK);
+    }
+    if !daemon.handed_over.load(Ordering::Relaxed) {
+        let _ = fs::remove_file(&socket);
+    }
+    println!("stopped");
+    Ok(())
+}
+
+// fetches without holding the lock so the control socket keeps answering; a stop cuts the
+// fetch short and keeps the old queue
+fn rebuild(core: &Mutex<Core>, stopping: &CancellationToken) {
+    let (settings, paths) = {
+        let core = core.lock().unwrap();
+        (core.settings().clone(), core.paths().clone())
+    };
+    let Some(all_dars) = fetch::load_sources_blocking(&settings, &paths, stopping) else {
+        return;
+    };
+    let mut core = core.lock().unwrap();
+    core.set_dars(all_dars);
+    core.build_queue(Utc::now());
+}
+
+// sent through org.freedesktop.Notifications on linux, with buttons where the server shows them
+fn show(daemon: &Arc<Daemon>, notification: &NotificationData) {
+    println!("msg {}", notification.id);
+    let mut toast = Notification::new();
+    toast
+        .appname("iUstadji")
+        .summary(&notification.title)
+        .body(&notification.description);
+    if supports_actions() {
+        let settings = daemon.core.lock().unwrap().settings().clone();
+        for action in Action::BUTTONS {
+            toast.action(action.id(), &action.label(&settings));
+        }
+    }
+    let handle = match toast.show() {
+        Ok(handle) => handle,
+        Err(err) => {
+            eprintln!("Failed to show notification: {}", err);
+            return;
+        }
+    };
+    let daemon = Arc::clone(daemon);
+    let notification = notification.clone();
+    thread::spawn(move || {
+        handle.wait_for_action(|action| {
+            if let Some(action) = Action::from_id(action) {
+                daemon.on_action(action, notification);
+            }
+        })
+    });
+}
+
+#[cfg(not(target_os = "macos"))]
+fn supports_actions() -> bool {
+    notify_rust::get_capabilities().is_ok_and(|caps| caps.iter().any(|cap| cap == "actions"))
+}
+
+#[cfg(target_os = "macos")]
+fn supports_actions() -> bool {
+    false
+}
+
+// writes the user unit next to the other ones, pointing at this binary
+pub fn install() -> Result<(), String> {
+    let bin = env::current_exe().map_err(|err| format!("Failed to locate iustadji: {}", err))?;
+    let dir = dirs::config_dir()
+        .map(|dir| dir.join("systemd/user"))
+        .ok_or_else(|| "No config directory".to_string())?;
+    fs::create_dir_all(&dir)
+        .map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?;
+    let unit: PathBuf = dir.join("iustadji.service");
+    fs::write(&unit, UNIT.replace("@BIN@", &bin.display().to_string()))
+        .map_err(|err| format!("Failed to write {}: {}", unit.display(), err))?;
+    println!("installed {}", unit.display());
+    println!(
+        "start it with: systemctl --user daemon-reload && systemctl --user enable --now iustadji"
+    );
+    Ok(())
+}
@@ -0,0 +1,352 @@
+#[cfg(unix)]
+mod daemon;
+
+use chrono::{DateTime, Utc};
+use clap::{Args, Parser, Subcommand};
+#[cfg(unix)]
+use iustadji_core::control::{self, Call, Status};
+use iustadji_core::{secrets, store, syndication, Core, Paths};
+use iustadji_domain::{DarsDate, Settings, SourceSecrets, Zone};
+use serde_json::Value;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Inspect feeds and drive iUstadji reminders from a terminal
+#[derive(Parser)]
+#[command(name = "iustadji", version)]
+struct Cli {
+    /// Directory holding settings.json, defaults to the one the app uses
+    #[arg(long, global = true)]
+    data_dir: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// List the notifications of every source, optionally between two dates
+    List {
+        #[arg(long, value_parser = parse_date)]
+        from: Option<DarsDate>,
+        #[arg(long, value_parser = parse_date)]
+        to: Option<DarsDate>,
+    },
+    /// Show the reminders that come next
+    Next {
+        /// Have the running instance show the next reminder right away
+        #[cfg(unix)]
+        #[arg(long)]
+        now: bool,
+    },
+    /// Mute reminders for the given number of minutes
+    Mute { minutes: u64 },
+    /// Unmute reminders
+    Unmute,
+    /// Show mute state and queue of the running instance
+    #[cfg(unix)]
+    Status,
+    /// Have the running instance re-read settings and feeds
+    #[cfg(unix)]
+    Reload,
+    /// Check a feed file and report every problem in it
+    Validate { file: PathBuf },
+    /// Run the reminders in the background without the app window
+    #[cfg(unix)]
+    Daemon {
+        /// Install a systemd user unit for it instead
+        #[arg(long)]
+        install: bool,
+    },
+    /// Read or change settings
+    Settings {
+        #[command(subcommand)]
+        command: SettingsCommand,
+    },
+    /// Store the token, passwords or header values of a source in the system keyring
+    Secret(SecretArgs),
+}
+
+#[derive(Args)]
+struct SecretArgs {
+    /// Url of the source as it is in the settings
+    url: String,
+    /// Ask for the token for bearer auth
+    #[arg(long)]
+    token: bool,
+    /// Ask for the password for basic auth
+    #[arg(long)]
+    password: bool,
+    /// Ask for the password for the proxy of the source
+    #[arg(long)]
+    proxy_password: bool,
+    /// Ask for the value of the header NAME, may be given more than once
+    #[arg(long = "header", value_name = "NAME")]
+    headers: Vec<String>,
+    /// Drop every stored secret of the source before storing the given ones
+    #[arg(long)]
+    clear: bool,
+}
+
+#[derive(Subcommand)]
+enum SettingsCommand {
+    /// Print all settings or a single one
+    Get { key: Option<String> },
+    /// Change a single setting, the value is read as JSON and falls back to a plain string
+    Set { key: String, value: String },
+}
+
+fn parse_date(date: &str) -> Result<DarsDate, String> {
+    DarsDate::parse(date).ok_or_else(|| format!("invalid date {}", date))
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let paths = cli.data_dir.map(Paths::in_dir).unwrap_or_default();
+    match run(cli.command, paths) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("iustadji: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Commands, paths: Paths) -> Result<(), String> {
+    // times are printed in the timezone of the settings, like the app shows them
+    // only read, commands like validate and secret leave a missing settings file missing
+    let zone = store::read_settings(&paths)
+        .ok()
+        .flatten()
+        .map_or(Zone::System, |settings| settings.zone());
+    match command {
+        Commands::List { from, to } => list(&mut Core::new(paths)?, from, to),
+        #[cfg(unix)]
+        Commands::Next { now: true } => {
+            control::call(&paths.socket, Call::Next).map(|status| print_status(status, zone))
+        }
+        Commands::Next { .. } => next(&mut Core::new(paths)?),
+        Commands::Mute { minutes } => {
+            // without a running instance the stored mute changes, picked up on its next check
+            #[cfg(unix)]
+            if control::is_running(&paths.socket) {
+                return control::call(&paths.socket, Call::Mute { minutes })
+                    .map(|status| print_status(status, zone));
+            }
+            let mut core = Core::new(paths)?;
+            core.apply_mute(minutes, Utc::now())?;
+            if let Some(until) = core.muted_until(Utc::now()) {
+                println!("muted until {}", format_time(zone, until));
+            }
+            Ok(())
+        }
+        Commands::Unmute => {
+            #[cfg(unix)]
+            if control::is_running(&paths.socket) {
+                return control::call(&paths.socket, Call::Unmute)
+                    .map(|status| print_status(status, zone));
+            }
+            Core::new(paths)?.unmute()
+        }
+        #[cfg(unix)]
+        Commands::Status => {
+            control::call(&paths.socket, Call::Status).map(|status| print_status(status, zone))
+        }
+        #[cfg(unix)]
+        Commands::Reload => {
+            control::call(&paths.socket, Call::Reload).map(|status| print_status(status, zone))
+        }
+        Commands::Validate { file } => validate(&file, &paths),
+        Commands::Settings { command } => settings(command, Core::new(paths)?),
+        Commands::Secret(args) => secret(args),
+        #[cfg(unix)]
+        Commands::Daemon { install: true } => daemon::install(),
+        #[cfg(unix)]
+        Commands::Daemon { install: false } => daemon::run(paths),
+    }
+}
+
+#[cfg(unix)]
+fn print_status(status: Option<Status>, zone: Zone) {
+    let Some(status) = status else {
+        return;
+    };
+    match status.muted_until {
+        Some(until) => println!("muted until {}", format_time(zone, until)),
+        None => println!("not muted"),
+    }
+    println!(
+        "{} in rotation, {} timed",
+        status.rotation, status.scheduled
+    );
+    if let Some(at) = status.next_at {
+        println!("next timed at {}", format_time(zone, at));
+    }
+}
+
+fn format_time(zone: Zone, at: DateTime<Utc>) -> String {
+    zone.local(at).format("%d.%m.%Y %H:%M").to_string()
+}
+
+fn list(core: &mut Core, from: Option<DarsDate>, to: Option<DarsDate>) -> Result<(), String> {
+    for dars in core.load_sources() {
+        if from.is_some_and(|from| dars.date < from) || to.is_some_and(|to| dars.date > to) {
+            continue;
+        }
+        for notification in &dars.notifications {
+            println!("{}  {}  {}", dars.date, notification.id, notification.title);
+        }
+    }
+    Ok(())
+}
+
+fn next(core: &mut Core) -> Result<(), String> {
+    let now = Utc::now();
+    let zone = core.settings().zone();
+    if let Some(until) = core.muted_until(now) {
+        println!("muted until {}", format_time(zone, until));
+    }
+    core.load_sources();
+    core.build_queue(now);
+
+    if let Some(scheduled) = core.queue().scheduled().first() {
+        println!(
+            "{}  {}\n  {}",
+            format_time(zone, scheduled.at),
+            scheduled.notification.title,
+            scheduled.notification.description
+        );
+    }
+    match core.queue().rotation().first() {
+        Some(notification) => println!(
+            "next  {}\n  {}",
+            notification.title, notification.description
+        ),
+        None if core.queue().is_empty() => println!("nothing to show in the current window"),
+        None => {}
+    }
+    Ok(())
+}
+
+fn validate(file: &Path, paths: &Paths) -> Result<(), String> {
+    // anchors come from the user's settings when there are any
+    let settings = store::read_settings(paths)?.unwrap_or_default();
+    let body =
+        fs::read(file).map_err(|err| format!("Failed to read {}: {}", file.display(), err))?;
+    let (all_dars, mut errors) = syndication::parse(&body, settings.zone())
+        .map_err(|err| format!("Failed to parse {}: {}", file.display(), err))?;
+
+    let mut count = 0;
+    for dars in &all_dars {
+        for (i, notification) in dars.notifications.iter().enumerate() {
+            count += 1;
+            if let Err(err) = notification.validate(&settings) {
+                errors.push(format!("{}: {}", dars.date, err));
+            }
+            if dars.notifications[..i]
+                .iter()
+                .any(|n| n.id == notification.id)
+            {
+                errors.push(format!("{}: duplicate id {}", dars.date, notification.id));
+            }
+        }
+    }
+
+    for err in &errors {
+        println!("{}", err);
+    }
+    if !errors.is_empty() {
+        return Err(format!("{} problems in {}", errors.len(), file.display()));
+    }
+    println!("{} dars, {} notifications, ok", all_dars.len(), count);
+    Ok(())
+}
+
+fn settings(command: SettingsCommand, mut core: Core) -> Result<(), String> {
+    let mut all = serde_json::to_value(core.settings())
+        .map_err(|err| format!("Failed to serialize settings to JSON: {}", err))?;
+    match command {
+        SettingsCommand::Get { key: None } => print_value(&all),
+        SettingsCommand::Get { key: Some(key) } => print_value(
+            all.get(&key)
+                .ok_or_else(|| format!("unknown setting {}", key))?,
+        ),
+        SettingsCommand::Set { key, value } => {
+            let field = all
+                .get_mut(&key)
+                .ok_or_else(|| format!("unknown setting {}", key))?;
+            *field = serde_json::from_str(&value).unwrap_or(Value::String(value));
+            let mut settings: Settings = serde_json::from_value(all)
+                .map_err(|err| format!("Invalid value for {}: {}", key, err))?;
+            let old = core.settings().clone();
+            let data_url = std::mem::replace(&mut settings.data_url, old.data_url.clone());
+            settings.set_data_url(data_url);
+            core.save_settings(settings)?;
+            // a removed source leaves nothing behind in the keyring
+            let kept = core.settings().all_sources();
+            for source in old.all_sources() {
+                if !kept.iter().any(|s| s.url == source.url) {
+                    if let Err(err) = secrets::delete(&source.url) {
+                        eprintln!("{}", err);
+                    }
+                }
+            }
+            // a running app or daemon would keep the old interval and sources until restarted
+            let socket = &core.paths().socket;
+            if control::is_running(socket) {
+                if let Err(err) = control::call(socket, Call::Reload) {
+                    eprintln!("Saved, but the running instance didn't reload: {}", err);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// the given secrets replace stored ones, the others are kept unless cleared
+fn secret(args: SecretArgs) -> Result<(), String> {
+    let mut stored = if args.clear {
+        SourceSecrets::default()
+    } else {
+        secrets::load(&args.url)?
+    };
+    if args.token {
+        stored.token = Some(read_secret("Token")?);
+    }
+    if args.password {
+        stored.password = Some(read_secret("Password")?);
+    }
+    if args.proxy_password {
+        stored.proxy_password = Some(read_secret("Proxy password")?);
+    }
+    for name in args.headers {
+        let value = read_secret(&format!("Value of {}", name))?;
+        stored.headers.insert(name, value);
+    }
+    secrets::save(&args.url, &stored)
+}
+
+// never from the command line, where shell history and ps would keep it: asked for without echo
+// on a terminal, otherwise one line each from stdin: token, password, proxy password, headers
+fn read_secret(what: &str) -> Result<String, String> {
+    let secret = if io::stdin().is_terminal() {
+        rpassword::prompt_password(format!("{}: ", what))
+    } else {
+        let mut line = String::new();
+        io::stdin().read_line(&mut line).map(|_| line)
+    };
+    secret
+        .map(|secret| secret.trim_end_matches(['\r', '\n']).to_string())
+        .map_err(|err| format!("Failed to read {}: {}", what.to_lowercase(), err))
+}
+
+fn print_value(value: &Value) {
+    match value {
+        Value::String(value) => println!("{}", value),
+        value => println!(
+            "{}",
+            serde_json::to_string_pretty(value).unwrap_or_default()
+        ),
+    }
+}
@@ -0,0 +1,43 @@
+// what a reminder offers besides opening its card, the ids come back from the notifier on a click
+use iustadji_domain::Settings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    // a click on the notification itself
+    Open,
+    Snooze,
+    // never show this reminder again, feeds skip_ids
+    Skip,
+    Learned,
+}
+
+impl Action {
+    // the buttons, for notifiers that can show them
+    pub const BUTTONS: [Action; 3] = [Action::Snooze, Action::Skip, Action::Learned];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Action::Open => "default",
+            Action::Snooze => "snooze",
+            Action::Skip => "skip",
+            Action::Learned => "learned",
+        }
+    }
+
+    pub fn label(self, settings: &Settings) -> String {
+        match self {
+            Action::Open => "Open".to_string(),
+            Action::Snooze => format!("Snooze {} min", settings.snooze_minutes),
+            Action::Skip => "Never show again".to_string(),
+            Action::Learned => "Learned".to_string(),
+        }
+    }
+
+    // `None` for "__closed" and anything else the notifier reports
+    pub fn from_id(id: &str) -> Option<Action> {
+        [Action::Open]
+            .into_iter()
+            .chain(Action::BUTTONS)
+            .find(|action| action.id() == id)
+    }
+}
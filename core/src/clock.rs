@@ -0,0 +1,79 @@
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use std::time::Instant;
+
+// larger differences between wall clock and monotonic time count as a jump
+pub const JUMP_THRESHOLD: TimeDelta = TimeDelta::minutes(2);
+// timed reminders overdue by more than this were missed, e.g. while suspended, and are dropped
+// instead of all showing up at once
+pub const MISSED_AFTER: TimeDelta = TimeDelta::minutes(5);
+
+// notices the local wall clock moving differently from monotonic time: a suspend (monotonic time
+// stands still meanwhile), the clock being set, a timezone or DST change; fed with the time of
+// the configured zone
+pub struct ClockWatch {
+    wall: NaiveDateTime,
+    mono: Instant,
+}
+
+impl ClockWatch {
+    pub fn new(now: NaiveDateTime) -> ClockWatch {
+        ClockWatch {
+            wall: now,
+            mono: Instant::now(),
+        }
+    }
+
+    // how far the wall clock moved beyond the time that passed since the last check
+    pub fn check(&mut self, now: NaiveDateTime) -> Option<TimeDelta> {
+        let mono = Instant::now();
+        let elapsed = TimeDelta::from_std(mono - self.mono).unwrap_or(TimeDelta::MAX);
+        let jump = (now - self.wall) - elapsed;
+        self.wall = now;
+        self.mono = mono;
+        (jump.abs() > JUMP_THRESHOLD).then_some(jump)
+    }
+}
+
+pub fn is_missed(at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    now - at > MISSED_AFTER
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wall(at: &str) -> NaiveDateTime {
+        at.parse().unwrap()
+    }
+
+    #[test]
+    fn steady_clocks_dont_jump() {
+        let mut clock = ClockWatch::new(wall("2024-03-15T09:00:00"));
+        assert_eq!(clock.check(wall("2024-03-15T09:00:01")), None);
+        assert_eq!(clock.check(wall("2024-03-15T09:01:30")), None);
+    }
+
+    #[test]
+    fn suspends_and_clock_changes_jump() {
+        let mut clock = ClockWatch::new(wall("2024-03-15T09:00:00"));
+        // woken up after a night, monotonic time stood still meanwhile
+        let jump = clock.check(wall("2024-03-16T07:00:00")).unwrap();
+        assert!(jump > TimeDelta::hours(21) && jump <= TimeDelta::hours(22));
+        // the next check only looks at the time since this one
+        assert_eq!(clock.check(wall("2024-03-16T07:00:01")), None);
+        // set back by hand, or a flight west
+        let jump = clock.check(wall("2024-03-16T04:00:01")).unwrap();
+        assert!(jump <= -TimeDelta::hours(3) + TimeDelta::seconds(1));
+        // DST ending moves local time back an hour
+        assert!(clock.check(wall("2024-03-16T03:00:01")).is_some());
+    }
+
+    #[test]
+    fn late_reminders_are_missed() {
+        let at: DateTime<Utc> = "2024-03-15T09:00:00Z".parse().unwrap();
+        assert!(!is_missed(at, at - TimeDelta::minutes(1)));
+        assert!(!is_missed(at, at + TimeDelta::minutes(4)));
+        assert!(!is_missed(at, at + MISSED_AFTER));
+        assert!(is_missed(at, at + TimeDelta::minutes(6)));
+    }
+}
@@ -0,0 +1,396 @@
+// the parsing is only used by the socket, which exists on unix
+#![cfg_attr(not(unix), allow(dead_code))]
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io;
+#[cfg(unix)]
+use std::io::{BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+#[cfg(unix)]
+use std::sync::Arc;
+#[cfg(unix)]
+use std::{fs, thread};
+
+// JSON-RPC 2.0 over a unix socket, one request and one response per line, e.g.
+// {"jsonrpc":"2.0","id":1,"method":"mute","params":{"minutes":30}}
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const CALL_FAILED: i64 = -32000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Call {
+    Mute { minutes: u64 },
+    Unmute,
+    // show the next reminder right away
+    Next,
+    Status,
+    // re-read settings and rebuild the queue
+    Reload,
+    // a second launch handing its arguments over before it exits; the daemon declines it and
+    // stops, leaving the reminders to the app
+    Open { args: Vec<String> },
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Status {
+    pub muted_until: Option<DateTime<Utc>>,
+    pub rotation: usize,
+    pub scheduled: usize,
+    pub next_at: Option<DateTime<Utc>>,
+}
+
+// mute, unmute and status answer with the status, next and reload with null
+pub type Reply = Result<Option<Status>, String>;
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: String,
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct MuteParams {
+    minutes: u64,
+}
+
+#[derive(Deserialize)]
+struct OpenParams {
+    #[serde(default)]
+    args: Vec<String>,
+}
+
+impl Call {
+    fn method(&self) -> &'static str {
+        match self {
+            Call::Mute { .. } => "mute",
+            Call::Unmute => "unmute",
+            Call::Next => "next",
+            Call::Status => "status",
+            Call::Reload => "reload",
+            Call::Open { .. } => "open",
+        }
+    }
+
+    fn params(&self) -> Value {
+        match self {
+            Call::Mute { minutes } => json!({ "minutes": minutes }),
+            Call::Open { args } => json!({ "args": args }),
+            _ => Value::Null,
+        }
+    }
+
+    fn from_request(method: &str, params: Value) -> Result<Call, (i64, String)> {
+        match method {
+            "mute" => serde_json::from_value::<MuteParams>(params)
+                .map(|p| Call::Mute { minutes: p.minutes })
+                .map_err(|err| (INVALID_PARAMS, err.to_string())),
+            "unmute" => Ok(Call::Unmute),
+            "next" => Ok(Call::Next),
+            "status" => Ok(Call::Status),
+            "reload" => Ok(Call::Reload),
+            "open" => serde_json::from_value::<OpenParams>(params)
+                .map(|p| Call::Open { args: p.args })
+                .map_err(|err| (INVALID_PARAMS, err.to_string())),
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method {}", method))),
+        }
+    }
+}
+
+// launch flags as calls (--mute <minutes>, --unmute, --next, --reload), everything else like
+// deep links is left to the caller
+pub fn calls_from_args(args: &[String]) -> (Vec<Call>, Vec<String>) {
+    let mut calls = vec![];
+    let mut rest = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--mute" => match args.next().and_then(|minutes| minutes.parse().ok()) {
+                Some(minutes) => calls.push(Call::Mute { minutes }),
+                None => eprintln!("--mute needs the minutes to mute for"),
+            },
+            "--unmute" => calls.push(Call::Unmute),
+            "--next" => calls.push(Call::Next),
+            "--reload" => calls.push(Call::Reload),
+            _ => rest.push(arg.clone()),
+        }
+    }
+    (calls, rest)
+}
+
+// binds the socket and answers every connection on its own thread; fails with AddrInUse while
+// another instance is listening, a socket file left behind by a crashed one is replaced
+#[cfg(unix)]
+pub fn serve<H>(path: &Path, handler: H) -> io::Result<()>
+where
+    H: Fn(Call) -> Reply + Send + Sync + 'static,
+{
+    let listener = match UnixListener::bind(path) {
+        Ok(listener) => listener,
+        Err(err) if err.kind() == io::ErrorKind::AddrInUse => {
+            if UnixStream::connect(path).is_ok() {
+                return Err(err);
+            }
+            fs::remove_file(path)?;
+            UnixListener::bind(path)?
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            UnixListener::bind(path)?
+        }
+        Err(err) => return Err(err),
+    };
+
+    let handler = Arc::new(handler);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let handler = Arc::clone(&handler);
+                    thread::spawn(move || {
+                        if let Err(err) = answer(stream, handler.as_ref()) {
+                            eprintln!("Control connection failed: {}", err);
+                        }
+                    });
+                }
+                Err(err) => eprintln!("Control socket failed: {}", err),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(unix)]
+fn answer(stream: UnixStream, handler: &dyn Fn(Call) -> Reply) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        if let Some(response) = respond(&line?, handler) {
+            writeln!(writer, "{}", response)?;
+        }
+    }
+    Ok(())
+}
+
+// the response to one line of a connection, blank lines get none
+fn respond(line: &str, handler: &dyn Fn(Call) -> Reply) -> Option<Value> {
+    if line.trim().is_empty() {
+        return None;
+    }
+    let response = match serde_json::from_str::<Request>(line) {
+        Err(err) => error(Value::Null, PARSE_ERROR, err.to_string()),
+        Ok(request) if request.jsonrpc != "2.0" => error(
+            request.id,
+            INVALID_REQUEST,
+            "jsonrpc must be 2.0".to_string(),
+        ),
+        Ok(request) => match Call::from_request(&request.method, request.params) {
+            Err((code, message)) => error(request.id, code, message),
+            Ok(call) => match handler(call) {
+                Ok(status) => json!({ "jsonrpc": "2.0", "id": request.id, "result": status }),
+                Err(message) => error(request.id, CALL_FAILED, message),
+            },
+        },
+    };
+    Some(response)
+}
+
+fn error(id: Value, code: i64, message: String) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+#[cfg(unix)]
+pub fn is_running(path: &Path) -> bool {
+    UnixStream::connect(path).is_ok()
+}
+
+// one request against the running instance
+#[cfg(unix)]
+pub fn call(path: &Path, call: Call) -> Reply {
+    let mut stream = UnixStream::connect(path)
+        .map_err(|err| format!("No running instance at {}: {}", path.display(), err))?;
+    let mut request = json!({ "jsonrpc": "2.0", "id": 1, "method": call.method() });
+    let params = call.params();
+    if !params.is_null() {
+        request["params"] = params;
+    }
+    writeln!(stream, "{}", request).map_err(|err| format!("Failed to send request: {}", err))?;
+
+    let mut line = String::new();
+    BufReader::new(stream)
+        .read_line(&mut line)
+        .map_err(|err| format!("Failed to read response: {}", err))?;
+    let mut response: Value =
+        serde_json::from_str(&line).map_err(|err| format!("Invalid response: {}", err))?;
+    if let Some(message) = response["error"]["message"].as_str() {
+        return Err(message.to_string());
+    }
+    serde_json::from_value(response["result"].take())
+        .map_err(|err| format!("Invalid response: {}", err))
+}
+
+// no control socket elsewhere: no other instance is ever found and a launch runs on its own
+#[cfg(not(unix))]
+pub fn serve<H>(_path: &Path, _handler: H) -> io::Result<()>
+where
+    H: Fn(Call) -> Reply + Send + Sync + 'static,
+{
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the control socket needs unix",
+    ))
+}
+
+#[cfg(not(unix))]
+pub fn is_running(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+pub fn call(_path: &Path, _call: Call) -> Reply {
+    Err("The control socket needs unix".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    // answers mute with an empty status and fails next
+    fn handler(call: Call) -> Reply {
+        match call {
+            Call::Next => Err("nothing to show".to_string()),
+            Call::Mute { .. } => Ok(Some(Status::default())),
+            _ => Ok(None),
+        }
+    }
+
+    #[test]
+    fn requests_become_calls() {
+        let mute = Call::from_request("mute", json!({ "minutes": 30 }));
+        assert_eq!(mute, Ok(Call::Mute { minutes: 30 }));
+        assert_eq!(Call::from_request("unmute", Value::Null), Ok(Call::Unmute));
+        let open = Call::from_request("open", json!({ "args": ["iustadji://dars/16.03.2024/1"] }));
+        assert_eq!(
+            open,
+            Ok(Call::Open {
+                args: args(&["iustadji://dars/16.03.2024/1"])
+            })
+        );
+        assert_eq!(
+            Call::from_request("open", json!({})),
+            Ok(Call::Open { args: vec![] })
+        );
+    }
+
+    #[test]
+    fn bad_requests_are_told_apart() {
+        let code = |result: Result<Call, (i64, String)>| result.unwrap_err().0;
+        assert_eq!(
+            code(Call::from_request("mute", Value::Null)),
+            INVALID_PARAMS
+        );
+        let minutes = json!({ "minutes": "soon" });
+        assert_eq!(code(Call::from_request("mute", minutes)), INVALID_PARAMS);
+        assert_eq!(
+            code(Call::from_request("quit", Value::Null)),
+            METHOD_NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn calls_survive_the_wire() {
+        let calls = [
+            Call::Mute { minutes: 5 },
+            Call::Unmute,
+            Call::Next,
+            Call::Status,
+            Call::Reload,
+            Call::Open {
+                args: args(&["--next"]),
+            },
+        ];
+        for call in calls {
+            assert_eq!(Call::from_request(call.method(), call.params()), Ok(call));
+        }
+    }
+
+    #[test]
+    fn lines_get_responses() {
+        let line = r#"{"jsonrpc":"2.0","id":7,"method":"mute","params":{"minutes":30}}"#;
+        let response = respond(line, &handler).unwrap();
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["result"]["rotation"], 0);
+
+        let line = r#"{"jsonrpc":"2.0","id":"a","method":"reload"}"#;
+        let response = respond(line, &handler).unwrap();
+        assert_eq!(response["id"], "a");
+        assert!(response["result"].is_null());
+        assert!(response.get("error").is_none());
+
+        assert!(respond("  ", &handler).is_none());
+    }
+
+    #[test]
+    fn errors_carry_their_codes() {
+        let code = |line: &str| respond(line, &handler).unwrap()["error"]["code"].clone();
+        assert_eq!(code("not json"), PARSE_ERROR);
+        assert_eq!(
+            code(r#"{"jsonrpc":"1.0","id":1,"method":"status"}"#),
+            INVALID_REQUEST
+        );
+        assert_eq!(
+            code(r#"{"jsonrpc":"2.0","id":1,"method":"quit"}"#),
+            METHOD_NOT_FOUND
+        );
+        assert_eq!(
+            code(r#"{"jsonrpc":"2.0","id":1,"method":"mute"}"#),
+            INVALID_PARAMS
+        );
+        let line = r#"{"jsonrpc":"2.0","id":1,"method":"next"}"#;
+        let response = respond(line, &handler).unwrap();
+        assert_eq!(response["error"]["code"], CALL_FAILED);
+        assert_eq!(response["error"]["message"], "nothing to show");
+    }
+
+    #[test]
+    fn launch_flags_become_calls() {
+        let launch = args(&[
+            "--mute",
+            "15",
+            "iustadji://subscribe?url=x",
+            "--unmute",
+            "--next",
+            "--reload",
+        ]);
+        let (calls, rest) = calls_from_args(&launch);
+        assert_eq!(
+            calls,
+            [
+                Call::Mute { minutes: 15 },
+                Call::Unmute,
+                Call::Next,
+                Call::Reload
+            ]
+        );
+        assert_eq!(rest, args(&["iustadji://subscribe?url=x"]));
+
+        let (calls, rest) = calls_from_args(&args(&["--mute", "later"]));
+        assert!(calls.is_empty());
+        assert!(rest.is_empty());
+    }
+}
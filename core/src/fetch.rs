@@ -0,0 +1,414 @@
+use crate::paths::Paths;
+use crate::secrets;
+use crate::signature;
+use crate::syndication;
+use iustadji_domain::feed::parse_feed;
+use iustadji_domain::ipc::{FetchState, FetchStatus};
+use iustadji_domain::{Dars, Settings, Source, SourceAuth, SourceSecrets, Zone};
+use rand::Rng;
+use reqwest::{Client, Proxy, RequestBuilder, StatusCode, Url};
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+const TRIES: u32 = 4;
+// the first retry waits about this long, every further one twice as long up to MAX_BACKOFF
+const BACKOFF: Duration = Duration::from_secs(2);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+// a feed as downloaded, not yet verified nor parsed
+pub struct Fetched {
+    pub body: Vec<u8>,
+    // only fetched for a source with a pinned key, `None` when the feed has no signature
+    pub signature: Option<Vec<u8>>,
+}
+
+// every source merged by date; the primary source falls back to the bundled feed when unreachable.
+// `None` once cancelled, every change of a source is reported through `progress`
+pub async fn load_sources(
+    settings: &Settings,
+    paths: &Paths,
+    progress: &(dyn Fn(FetchStatus) + Sync),
+    cancel: &CancellationToken,
+) -> Option<Vec<Dars>> {
+    let mut all_dars = vec![];
+    for (i, source) in settings.all_sources().iter().enumerate() {
+        let report = |state| {
+            progress(FetchStatus {
+                url: source.url.clone(),
+                state,
+            })
+        };
+        let fetched = match prepare(source) {
+            Ok((client, secrets)) => fetch_source(&client, source, &secrets, &report, cancel).await,
+            Err(err) => Some(Err(err)),
+        };
+        let loaded = match fetched {
+            Some(Ok(fetched)) => accept(source, fetched, settings.zone()),
+            Some(Err(error)) => Err(FetchState::Failed { error }),
+            None => {
+                report(FetchState::Cancelled);
+                return None;
+            }
+        };
+        match loaded {
+            Ok(dars) => {
+                report(FetchState::Done { count: dars.len() });
+                all_dars.extend(dars);
+            }
+            Err(state) => {
+                if let FetchState::Failed { error } | FetchState::Rejected { error } = &state {
+                    eprintln!("Failed to load {}: {}", source.url, error);
+                }
+                report(state);
+                if i == 0 {
+                    all_dars.extend(load_fallback(paths));
+                }
+            }
+        }
+    }
+    Some(merge(all_dars))
+}
+
+// for front-ends without an async runtime of their own, `None` once cancelled
+pub fn load_sources_blocking(
+    settings: &Settings,
+    paths: &Paths,
+    cancel: &CancellationToken,
+) -> Option<Vec<Dars>> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build();
+    match runtime {
+        Ok(runtime) => runtime.block_on(load_sources(settings, paths, &|_| {}, cancel)),
+        Err(err) => {
+            eprintln!("Failed to start fetching: {}", err);
+            Some(load_fallback(paths))
+        }
+    }
+}
+
+// the client for `source` and its secrets, the keyring is only asked when the source has any
+fn prepare(source: &Source) -> Result<(Client, SourceSecrets), String> {
+    let secrets = if source.needs_secrets() {
+        secrets::load(&source.url)?
+    } else {
+        SourceSecrets::default()
+    };
+    let client = client(source, &secrets)?;
+    // a missing token or a bad url fails here instead of on every retry
+    let _ = request(&client, source, &secrets, &source.url)?;
+    if source.public_key.is_some() {
+        signature::signature_url(&source.url)?;
+    }
+    Ok((client, secrets))
+}
+
+// through the proxy of the source, if it has one
+pub fn client(source: &Source, secrets: &SourceSecrets) -> Result<Client, String> {
+    let mut builder = Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(TIMEOUT);
+    if let Some(proxy) = &source.proxy {
+        // the error names the proxy as configured, never with its password
+        let invalid = |err: &dyn std::fmt::Display| format!("Invalid proxy {}: {}", proxy, err);
+        let mut url = Url::parse(proxy).map_err(|err| invalid(&err))?;
+        if let Some(password) = &secrets.proxy_password {
+            url.set_password(Some(password))
+                .map_err(|()| invalid(&"no host"))?;
+        }
+        builder = builder.proxy(Proxy::all(url).map_err(|err| invalid(&err))?);
+    }
+    builder
+        .build()
+        .map_err(|err| format!("Failed to set up HTTP: {}", err))
+}
+
+// the feed and its signature are asked for with the same credentials
+fn request(
+    client: &Client,
+    source: &Source,
+    secrets: &SourceSecrets,
+    url: &str,
+) -> Result<RequestBuilder, String> {
+    let mut request = client.get(url);
+    match &source.auth {
+        SourceAuth::None => {}
+        SourceAuth::Bearer => {
+            let token = secrets.token.as_deref().ok_or("No token stored")?;
+            request = request.bearer_auth(token);
+        }
+        SourceAuth::Basic { username } => {
+            request = request.basic_auth(username, secrets.password.as_deref());
+        }
+    }
+    for name in &source.headers {
+        let value = secrets
+            .headers
+            .get(name)
+            .ok_or_else(|| format!("No value stored for header {}", name))?;
+        request = request.header(name, value);
+    }
+    Ok(request)
+}
+
+// `None` when cancelled, before each try and while waiting for the next one
+pub async fn fetch_source(
+    client: &Client,
+    source: &Source,
+    secrets: &SourceSecrets,
+    report: &(dyn Fn(FetchState) + Sync),
+    cancel: &CancellationToken,
+) -> Option<Result<Fetched, String>> {
+    let mut tried = 0;
+    loop {
+        tried += 1;
+        report(FetchState::Fetching {
+            attempt: tried,
+            tries: TRIES,
+        });
+        match cancel
+            .run_until_cancelled(fetch_once(client, source, secrets))
+            .await?
+        {
+            Ok(fetched) => return Some(Ok(fetched)),
+            Err(err) if tried >= TRIES => return Some(Err(err)),
+            Err(err) => {
+                let delay = backoff(tried);
+                eprintln!("{}, retrying in {}s", err, delay.as_secs());
+                report(FetchState::Retrying {
+                    error: err,
+                    seconds: delay.as_secs(),
+                });
+                cancel
+                    .run_until_cancelled(tokio::time::sleep(delay))
+                    .await?;
+            }
+        }
+    }
+}
+
+// exponential with jitter, so clients that failed together don't retry together
+fn backoff(tried: u32) -> Duration {
+    let delay = BACKOFF
+        .saturating_mul(1 << (tried - 1).min(16))
+        .min(MAX_BACKOFF);
+    rand::thread_rng().gen_range(delay / 2..=delay)
+}
+
+async fn fetch_once(
+    client: &Client,
+    source: &Source,
+    secrets: &SourceSecrets,
+) -> Result<Fetched, String> {
+    let response = request(client, source, secrets, &source.url)?
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| format!("Failed to fetch feed: {}", err))?;
+    let body = response
+        .bytes()
+        .await
+        .map_err(|err| format!("Failed to read response body: {}", err))?
+        .to_vec();
+    let signature = match source.public_key {
+        Some(_) => fetch_signature(client, source, secrets).await?,
+        None => None,
+    };
+    Ok(Fetched { body, signature })
+}
+
+// a missing signature is not worth a retry, the feed gets rejected for it
+async fn fetch_signature(
+    client: &Client,
+    source: &Source,
+    secrets: &SourceSecrets,
+) -> Result<Option<Vec<u8>>, String> {
+    let url = signature::signature_url(&source.url)?;
+    let response = request(client, source, secrets, url.as_str())?
+        .send()
+        .await
+        .map_err(|err| format!("Failed to fetch signature: {}", err))?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let signature = response
+        .error_for_status()
+        .map_err(|err| format!("Failed to fetch signature: {}", err))?
+        .bytes()
+        .await
+        .map_err(|err| format!("Failed to read signature: {}", err))?;
+    Ok(Some(signature.to_vec()))
+}
+
+// checked once downloaded, a feed that doesn't match its pinned key is never parsed
+pub fn accept(source: &Source, fetched: Fetched, zone: Zone) -> Result<Vec<Dars>, FetchState> {
+    if let Some(key) = &source.public_key {
+        let reject = |error| FetchState::Rejected { error };
+        let signature = fetched
+            .signature
+            .ok_or_else(|| reject(format!("No signature at {}.sig", source.url)))?;
+        signature::verify(key, &fetched.body, &signature).map_err(reject)?;
+    }
+    let (all_dars, errors) =
+        syndication::parse(&fetched.body, zone).map_err(|err| FetchState::Failed {
+            error: format!("Failed to parse feed: {}", err),
+        })?;
+    for err in errors {
+        eprintln!("Skipping dars {}", err);
+    }
+    Ok(all_dars)
+}
+
+fn load_fallback(paths: &Paths) -> Vec<Dars> {
+    match fs::read_to_string(&paths.dars_fallback).map(|body| parse_feed(&body)) {
+        Ok(Ok((all_dars, _))) => all_dars,
+        Ok(Err(err)) => {
+            eprintln!("Failed to parse {}: {}", paths.dars_fallback.display(), err);
+            vec![]
+        }
+        Err(err) => {
+            eprintln!("Failed to read {}: {}", paths.dars_fallback.display(), err);
+            vec![]
+        }
+    }
+}
+
+// one entry per date, newest first, the first notification with a given id wins
+fn merge(all_dars: Vec<Dars>) -> Vec<Dars> {
+    let mut by_date: BTreeMap<_, Dars> = BTreeMap::new();
+    for dars in all_dars {
+        match by_date.get_mut(&dars.date) {
+            Some(merged) => {
+                for notification in dars.notifications {
+                    if merged.notifications.iter().all(|n| n.id != notification.id) {
+                        merged.notifications.push(notification);
+                    }
+                }
+            }
+            None => {
+                by_date.insert(dars.date, dars);
+            }
+        }
+    }
+    by_date.into_values().rev().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::prelude::{Engine, BASE64_STANDARD};
+    use iustadji_domain::{DarsDate, NotificationData};
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    const FEED: &str =
+        r#"[{"date":"16.03.2024","notifications":[{"id":"1","title":"t","description":"d"}]}]"#;
+
+    fn utc() -> Zone {
+        Zone::parse(Some("UTC")).unwrap()
+    }
+
+    // a source pinned to a fresh key and the signature of FEED made with it
+    fn signed() -> (Source, Vec<u8>) {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let source = Source {
+            public_key: Some(BASE64_STANDARD.encode(key_pair.public_key())),
+            ..Source::new("https://example.org/dars.json")
+        };
+        (source, key_pair.sign(FEED.as_bytes()).as_ref().to_vec())
+    }
+
+    fn fetched(body: &str, signature: Option<Vec<u8>>) -> Fetched {
+        Fetched {
+            body: body.as_bytes().to_vec(),
+            signature,
+        }
+    }
+
+    #[test]
+    fn unpinned_feeds_are_only_parsed() {
+        let source = Source::new("https://example.org/dars.json");
+        let all_dars = accept(&source, fetched(FEED, None), utc()).unwrap();
+        assert_eq!(all_dars[0].notifications[0].id, "1");
+        let failed = accept(&source, fetched("<html>", None), utc());
+        assert!(matches!(failed, Err(FetchState::Failed { .. })));
+    }
+
+    #[test]
+    fn pinned_feeds_need_a_matching_signature() {
+        let (source, signature) = signed();
+        let accepted = accept(&source, fetched(FEED, Some(signature.clone())), utc());
+        assert_eq!(accepted.unwrap().len(), 1);
+
+        let missing = accept(&source, fetched(FEED, None), utc());
+        assert!(matches!(missing, Err(FetchState::Rejected { .. })));
+        let tampered = FEED.replace("\"t\"", "\"x\"");
+        let tampered = accept(&source, fetched(&tampered, Some(signature)), utc());
+        assert!(matches!(tampered, Err(FetchState::Rejected { .. })));
+        let (_, other) = signed();
+        let other = accept(&source, fetched(FEED, Some(other)), utc());
+        assert!(matches!(other, Err(FetchState::Rejected { .. })));
+    }
+
+    fn dars(date: &str, ids: &[&str]) -> Dars {
+        let notifications = ids
+            .iter()
+            .map(|id| NotificationData {
+                id: id.to_string(),
+                title: date.to_string(),
+                description: String::new(),
+                time: None,
+                active: vec![],
+            })
+            .collect();
+        Dars {
+            date: DarsDate::parse(date).unwrap(),
+            notifications,
+        }
+    }
+
+    #[test]
+    fn sources_merge_by_date() {
+        let merged = merge(vec![
+            dars("15.03.2024", &["1", "2"]),
+            dars("16.03.2024", &["1"]),
+            dars("15.03.2024", &["2", "3"]),
+        ]);
+        let dates: Vec<String> = merged.iter().map(|d| d.date.to_string()).collect();
+        assert_eq!(dates, ["16.03.2024", "15.03.2024"]);
+        let ids: Vec<&str> = merged[1]
+            .notifications
+            .iter()
+            .map(|n| n.id.as_str())
+            .collect();
+        assert_eq!(ids, ["1", "2", "3"]);
+        // the earlier source wins a clash of ids
+        assert!(merged[1]
+            .notifications
+            .iter()
+            .all(|n| n.title == "15.03.2024"));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_limit() {
+        for _ in 0..20 {
+            let first = backoff(1);
+            assert!(
+                first >= Duration::from_secs(1) && first <= BACKOFF,
+                "{:?}",
+                first
+            );
+            let third = backoff(3);
+            assert!(third >= Duration::from_secs(4) && third <= Duration::from_secs(8));
+            for tried in [7, 40, u32::MAX] {
+                let late = backoff(tried);
+                assert!(late >= MAX_BACKOFF / 2 && late <= MAX_BACKOFF, "{:?}", late);
+            }
+        }
+    }
+}
@@ -0,0 +1,262 @@
+// Feed loading, queue building and muting without any tauri dependency, so the same scheduler
+// can back the desktop app, a terminal front-end or a CI job.
+pub mod action;
+pub mod clock;
+pub mod control;
+pub mod fetch;
+pub mod paths;
+pub mod queue;
+pub mod secrets;
+pub mod signature;
+pub mod store;
+pub mod syndication;
+
+pub use action::Action;
+pub use paths::Paths;
+pub use queue::{Queue, ScheduledNotification};
+
+use chrono::{DateTime, Duration, Utc};
+use iustadji_domain::{Dars, NotificationData, Settings};
+use tokio_util::sync::CancellationToken;
+
+// everything a front-end needs to drive reminders, one instance per running app
+pub struct Core {
+    paths: Paths,
+    settings: Settings,
+    dars: Vec<Dars>,
+    queue: Queue,
+}
+
+impl Core {
+    pub fn new(paths: Paths) -> Result<Core, String> {
+        let settings = store::load_settings(&paths)?;
+        Ok(Core {
+            paths,
+            settings,
+            dars: vec![],
+            queue: Queue::default(),
+        })
+    }
+
+    pub fn paths(&self) -> &Paths {
+        &self.paths
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn dars(&self) -> &[Dars] {
+        &self.dars
+    }
+
+    pub fn queue(&self) -> &Queue {
+        &self.queue
+    }
+
+    pub fn save_settings(&mut self, settings: Settings) -> Result<(), String> {
+        store::save_settings(&self.paths, &settings)?;
+        self.settings = settings;
+        Ok(())
+    }
+
+    pub fn reload_settings(&mut self) -> Result<(), String> {
+        self.settings = store::load_settings(&self.paths)?;
+        Ok(())
+    }
+
+    // for front-ends that fetch without holding on to the core
+    pub fn set_dars(&mut self, dars: Vec<Dars>) {
+        self.dars = dars;
+    }
+
+    // fetches every configured source, blocking until each one answered or fell back to the cache
+    pub fn load_sources(&mut self) -> &[Dars] {
+        self.dars =
+            fetch::load_sources_blocking(&self.settings, &self.paths, &CancellationToken::new())
+                .unwrap_or_default();
+        &self.dars
+    }
+
+    // snoozed reminders outlive the rebuild
+    pub fn build_queue(&mut self, now: DateTime<Utc>) {
+        self.queue.rebuild(&self.dars, &self.settings, now);
+    }
+
+    // timed reminders only, from the feeds already loaded
+    pub fn reschedule(&mut self, now: DateTime<Utc>) {
+        self.queue.reschedule(&self.dars, &self.settings, now);
+    }
+
+    pub fn snooze(&mut self, notification: NotificationData, minutes: u64, now: DateTime<Utc>) {
+        let at = now + Duration::minutes(minutes as i64);
+        self.queue.snooze(notification, at);
+    }
+
+    // "Never show again" and "Learned", both keep the reminder out of the next queue
+    pub fn skip(&mut self, id: &str) -> Result<(), String> {
+        self.update_settings(|settings| settings.skip(id))
+    }
+
+    pub fn mark_learned(&mut self, id: &str) -> Result<(), String> {
+        self.update_settings(|settings| settings.mark_learned(id))
+    }
+
+    // the next rotating reminder, `None` while muted or once the rotation is used up
+    pub fn next_notification(&mut self, now: DateTime<Utc>) -> Option<NotificationData> {
+        if self.is_muted(now) {
+            return None;
+        }
+        self.queue.next_notification()
+    }
+
+    // see `Queue::due_notifications`, against the mute of the settings
+    pub fn due_notifications(&mut self, now: DateTime<Utc>) -> Vec<NotificationData> {
+        let muted_until = self.muted_until(now);
+        self.queue.due_notifications(now, muted_until)
+    }
+
+    pub fn status(&self, now: DateTime<Utc>) -> control::Status {
+        control::Status {
+            muted_until: self.muted_until(now),
+            rotation: self.queue.rotation().len(),
+            scheduled: self.queue.scheduled().len(),
+            next_at: self.queue.scheduled().first().map(|s| s.at),
+        }
+    }
+
+    // the end of the mute in effect at `now`
+    pub fn muted_until(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.settings.active_mute(now)
+    }
+
+    pub fn is_muted(&self, now: DateTime<Utc>) -> bool {
+        self.muted_until(now).is_some()
+    }
+
+    pub fn apply_mute(&mut self, minutes: u64, now: DateTime<Utc>) -> Result<(), String> {
+        self.mute_until(now + Duration::minutes(minutes as i64))
+    }
+
+    pub fn mute_until(&mut self, until: DateTime<Utc>) -> Result<(), String> {
+        self.save_mute(Some(until))
+    }
+
+    pub fn unmute(&mut self) -> Result<(), String> {
+        self.save_mute(None)
+    }
+
+    fn save_mute(&mut self, until: Option<DateTime<Utc>>) -> Result<(), String> {
+        // not one of the tray presets
+        self.update_settings(|settings| {
+            settings.muted_until = until;
+            settings.mute_preset = None;
+        })
+    }
+
+    // applied to the stored settings rather than to the copy here, which may be older than
+    // changes made by the app or the cli; the copy only gets the same change, the others wait
+    // for a reload
+    fn update_settings(&mut self, change: impl Fn(&mut Settings)) -> Result<(), String> {
+        let mut settings = store::load_settings(&self.paths)?;
+        change(&mut settings);
+        store::save_settings(&self.paths, &settings)?;
+        change(&mut self.settings);
+        Ok(())
+    }
+
+    // picks up a mute changed by another front-end
+    pub fn reload_mute(&mut self) {
+        match store::load_settings(&self.paths) {
+            Ok(settings) => {
+                self.settings.muted_until = settings.muted_until;
+                self.settings.mute_preset = settings.mute_preset;
+            }
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn core() -> (TempDir, Core) {
+        let dir = tempfile::tempdir().unwrap();
+        let core = Core::new(Paths::in_dir(dir.path())).unwrap();
+        (dir, core)
+    }
+
+    fn stored(core: &Core) -> Settings {
+        store::load_settings(core.paths()).unwrap()
+    }
+
+    #[test]
+    fn actions_keep_changes_made_elsewhere() {
+        let (_dir, mut core) = core();
+        // the app or the cli changes settings.json after this core read it
+        let mut elsewhere = stored(&core);
+        elsewhere.interval = 42;
+        elsewhere.skip("14.03.2024a");
+        store::save_settings(core.paths(), &elsewhere).unwrap();
+
+        core.skip("15.03.2024b").unwrap();
+        core.mark_learned("15.03.2024c").unwrap();
+        core.apply_mute(30, Utc::now()).unwrap();
+        let settings = stored(&core);
+        assert_eq!(settings.interval, 42);
+        assert_eq!(settings.skip_ids, ["14.03.2024a", "15.03.2024b"]);
+        assert_eq!(settings.learned_ids, ["15.03.2024c"]);
+        assert!(settings.muted_until.is_some());
+
+        assert!(core.settings().is_hidden("15.03.2024b"));
+        assert!(core.settings().is_hidden("15.03.2024c"));
+        core.unmute().unwrap();
+        assert!(stored(&core).muted_until.is_none());
+    }
+
+    #[test]
+    fn mutes_from_here_drop_the_tray_preset() {
+        let (_dir, mut core) = core();
+        // the app muted from its tray
+        let mut elsewhere = stored(&core);
+        elsewhere.muted_until = Some(Utc::now() + Duration::hours(1));
+        elsewhere.mute_preset = Some("mute_60".to_string());
+        store::save_settings(core.paths(), &elsewhere).unwrap();
+        core.reload_mute();
+        assert_eq!(core.settings().mute_preset.as_deref(), Some("mute_60"));
+
+        core.apply_mute(30, Utc::now()).unwrap();
+        assert!(stored(&core).mute_preset.is_none());
+        assert!(core.settings().mute_preset.is_none());
+    }
+
+    #[test]
+    fn snoozed_reminders_wait_for_the_mute_to_end() {
+        let (_dir, mut core) = core();
+        let now = Utc::now();
+        let reminder = NotificationData {
+            id: "15.03.2024a".to_string(),
+            title: "a".to_string(),
+            description: String::new(),
+            time: None,
+            active: vec![],
+        };
+        core.snooze(reminder, 5, now);
+        core.apply_mute(30, now).unwrap();
+
+        let later = now + Duration::minutes(10);
+        assert!(core.due_notifications(later).is_empty());
+        assert!(core.next_notification(later).is_none());
+        let snoozed: Vec<_> = core.queue().snoozed().collect();
+        assert_eq!(snoozed.len(), 1);
+        assert_eq!(Some(snoozed[0].at), core.muted_until(later));
+
+        let after = now + Duration::minutes(31);
+        let shown = core.due_notifications(after);
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].id, "15.03.2024a");
+        assert_eq!(core.queue().snoozed().count(), 0);
+    }
+}
@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+
+// where settings live and which bundled feed to fall back to when every source is unreachable
+#[derive(Debug, Clone)]
+pub struct Paths {
+    pub settings: PathBuf,
+    pub dars_fallback: PathBuf,
+    // control socket of the running instance
+    pub socket: PathBuf,
+}
+
+impl Paths {
+    pub fn in_dir(dir: impl Into<PathBuf>) -> Paths {
+        let dir = dir.into();
+        Paths {
+            settings: dir.join("settings.json"),
+            dars_fallback: dir.join("dars.json"),
+            socket: dir.join("iustadji.sock"),
+        }
+    }
+}
+
+impl Default for Paths {
+    #[cfg(target_os = "macos")]
+    fn default() -> Self {
+        Paths::in_dir("/Applications/iUstadji.app/Contents/Resources/data")
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn default() -> Self {
+        let dir = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+        Paths::in_dir(dir.join("iustadji"))
+    }
+}
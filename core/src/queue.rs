@@ -0,0 +1,464 @@
+use crate::clock;
+use chrono::{DateTime, Utc};
+use iustadji_domain::{composite_id, rules, Dars, NotificationData, Settings};
+use rand::seq::SliceRandom;
+
+#[derive(Debug, Clone)]
+pub struct ScheduledNotification {
+    pub at: DateTime<Utc>,
+    pub notification: NotificationData,
+    // put back by the user rather than timed by the feed
+    pub snoozed: bool,
+}
+
+// the rotating reminders in order plus the one-shot timed ones, ids are made unique per dars date
+#[derive(Debug, Clone, Default)]
+pub struct Queue {
+    rotation: Vec<NotificationData>,
+    cursor: usize,
+    scheduled: Vec<ScheduledNotification>,
+}
+
+impl Queue {
+    // dates and fixed times are read in the timezone of the settings
+    pub fn build(all_dars: &[Dars], settings: &Settings, now: DateTime<Utc>) -> Queue {
+        let mut rotation: Vec<NotificationData> = vec![];
+        let mut scheduled: Vec<ScheduledNotification> = vec![];
+        let zone = settings.zone();
+        let today = zone.local(now).date();
+        let range = settings.dars_range(today);
+
+        for dars in all_dars {
+            let dars_date = dars.date.naive();
+            for notification in &dars.notifications {
+                if let Err(err) = notification.validate(settings) {
+                    eprintln!("Skipping notification {}", err);
+                    continue;
+                }
+
+                // seasonal notifications follow today's date instead of the dars window
+                let seasonal = !notification.active.is_empty();
+                if seasonal && !rules::is_active(&notification.active, today) {
+                    continue;
+                }
+                let notification_date = if seasonal { today } else { dars_date };
+
+                let id = composite_id(dars.date, &notification.id);
+                if settings.is_hidden(&id) {
+                    continue;
+                }
+                let notif = NotificationData {
+                    id,
+                    ..notification.clone()
+                };
+
+                // timed notifications are one-shot reminders outside the rotation
+                if let Some(time) = notif.time.as_deref().and_then(|t| settings.resolve_time(t)) {
+                    let at = zone.resolve(notification_date.and_time(time));
+                    if let Some(at) = at.filter(|at| *at > now) {
+                        scheduled.push(ScheduledNotification {
+                            at,
+                            notification: notif,
+                            snoozed: false,
+                        });
+                    }
+                    continue;
+                }
+
+                if seasonal {
+                    rotation.push(notif);
+                } else if let Some((start_date, end_date)) = range {
+                    if notification_date >= start_date && notification_date <= end_date {
+                        rotation.push(notif);
+                    }
+                }
+            }
+        }
+        if settings.pick_random {
+            rotation.shuffle(&mut rand::thread_rng());
+        }
+        scheduled.sort_by_key(|s| s.at);
+
+        Queue {
+            rotation,
+            cursor: 0,
+            scheduled,
+        }
+    }
+
+    pub fn rotation(&self) -> &[NotificationData] {
+        &self.rotation
+    }
+
+    pub fn scheduled(&self) -> &[ScheduledNotification] {
+        &self.scheduled
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rotation.is_empty() && self.scheduled.is_empty()
+    }
+
+    // `None` once every rotating reminder was handed out, the caller rebuilds then
+    pub fn next_notification(&mut self) -> Option<NotificationData> {
+        let notification = self.rotation.get(self.cursor).cloned();
+        if notification.is_some() {
+            self.cursor += 1;
+        }
+        notification
+    }
+
+    // a reminder is snoozed once, snoozing it again moves it
+    pub fn snooze(&mut self, notification: NotificationData, at: DateTime<Utc>) {
+        self.cancel_snooze(&notification.id);
+        let index = self.scheduled.partition_point(|s| s.at <= at);
+        self.scheduled.insert(
+            index,
+            ScheduledNotification {
+                at,
+                notification,
+                snoozed: true,
+            },
+        );
+    }
+
+    pub fn cancel_snooze(&mut self, id: &str) -> bool {
+        let before = self.scheduled.len();
+        self.scheduled
+            .retain(|s| !(s.snoozed && s.notification.id == id));
+        self.scheduled.len() != before
+    }
+
+    pub fn snoozed(&self) -> impl Iterator<Item = &ScheduledNotification> {
+        self.scheduled.iter().filter(|s| s.snoozed)
+    }
+
+    // a fresh queue from the feeds, snoozed reminders outlive it
+    pub fn rebuild(&mut self, all_dars: &[Dars], settings: &Settings, now: DateTime<Utc>) {
+        let snoozed: Vec<_> = self.scheduled.drain(..).filter(|s| s.snoozed).collect();
+        *self = Queue::build(all_dars, settings, now);
+        for snoozed in snoozed {
+            self.snooze(snoozed.notification, snoozed.at);
+        }
+    }
+
+    // after a clock jump: the timed reminders are resolved again, the rotation keeps its place
+    // and snoozed reminders stay as they are
+    pub fn reschedule(&mut self, all_dars: &[Dars], settings: &Settings, now: DateTime<Utc>) {
+        let fresh = Queue::build(all_dars, settings, now);
+        self.scheduled.retain(|s| s.snoozed);
+        self.scheduled.extend(fresh.scheduled);
+        self.scheduled.sort_by_key(|s| s.at);
+    }
+
+    pub fn take_due(&mut self, now: DateTime<Utc>) -> Vec<ScheduledNotification> {
+        let (due, pending) = self.scheduled.drain(..).partition(|s| s.at <= now);
+        self.scheduled = pending;
+        due
+    }
+
+    // timed reminders that came due, dropped rather than postponed while muted or when missed;
+    // one the user snoozed is always shown, once the mute is over
+    pub fn due_notifications(
+        &mut self,
+        now: DateTime<Utc>,
+        muted_until: Option<DateTime<Utc>>,
+    ) -> Vec<NotificationData> {
+        let mut shown = vec![];
+        for scheduled in self.take_due(now) {
+            match muted_until {
+                Some(until) if scheduled.snoozed => self.snooze(scheduled.notification, until),
+                Some(_) => {}
+                None if scheduled.snoozed || !clock::is_missed(scheduled.at, now) => {
+                    shown.push(scheduled.notification)
+                }
+                None => {}
+            }
+        }
+        shown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iustadji_domain::{DarsDate, DarsWindow};
+
+    // Friday 15.03.2024, the fifth of Ramadan 1445
+    fn now() -> DateTime<Utc> {
+        "2024-03-15T09:00:00Z".parse().unwrap()
+    }
+
+    fn settings(window: DarsWindow) -> Settings {
+        Settings {
+            dars_window: window,
+            dars_start_date: date("10.03.2024"),
+            dars_end_date: date("14.03.2024"),
+            timezone: Some("UTC".to_string()),
+            ..Settings::default()
+        }
+    }
+
+    fn date(date: &str) -> DarsDate {
+        DarsDate::parse(date).unwrap()
+    }
+
+    fn notification(id: &str, time: Option<&str>, active: &[&str]) -> NotificationData {
+        NotificationData {
+            id: id.to_string(),
+            title: id.to_string(),
+            description: String::new(),
+            time: time.map(str::to_string),
+            active: active.iter().map(|rule| rule.to_string()).collect(),
+        }
+    }
+
+    fn dars(day: &str, notifications: Vec<NotificationData>) -> Dars {
+        Dars {
+            date: date(day),
+            notifications,
+        }
+    }
+
+    fn plain(day: &str) -> Dars {
+        dars(day, vec![notification("1", None, &[])])
+    }
+
+    fn rotation(queue: &Queue) -> Vec<&str> {
+        queue.rotation().iter().map(|n| n.id.as_str()).collect()
+    }
+
+    fn scheduled(queue: &Queue) -> Vec<(String, &str)> {
+        queue
+            .scheduled()
+            .iter()
+            .map(|s| (s.at.to_rfc3339(), s.notification.id.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn windows_pick_the_dars() {
+        let all_dars = [
+            plain("16.03.2024"),
+            plain("14.03.2024"),
+            plain("10.03.2024"),
+            plain("08.03.2024"),
+            plain("01.01.2024"),
+        ];
+        let build = |window| Queue::build(&all_dars, &settings(window), now());
+        assert_eq!(
+            rotation(&build(DarsWindow::Custom)),
+            ["14.03.20241", "10.03.20241"]
+        );
+        assert_eq!(
+            rotation(&build(DarsWindow::Last7Days)),
+            ["14.03.20241", "10.03.20241"]
+        );
+        assert_eq!(
+            rotation(&build(DarsWindow::Last30Days)),
+            ["14.03.20241", "10.03.20241", "08.03.20241"]
+        );
+        assert_eq!(
+            rotation(&build(DarsWindow::ThisHijriMonth)),
+            ["16.03.20241", "14.03.20241"]
+        );
+        assert_eq!(build(DarsWindow::All).rotation().len(), 5);
+    }
+
+    #[test]
+    fn seasonal_reminders_follow_today() {
+        let all_dars = [dars(
+            "01.01.2024",
+            vec![
+                notification("friday", None, &["friday"]),
+                notification("thursday", None, &["thursday"]),
+                notification("ramadan", None, &["ramadan:1-10"]),
+                notification("late_ramadan", None, &["ramadan:21-30"]),
+                notification("either", None, &["shawwal", "friday"]),
+                notification("jumuah", Some("12:30"), &["friday"]),
+            ],
+        )];
+        let queue = Queue::build(&all_dars, &settings(DarsWindow::Custom), now());
+        assert_eq!(
+            rotation(&queue),
+            ["01.01.2024friday", "01.01.2024ramadan", "01.01.2024either"]
+        );
+        // timed on today rather than on the long gone dars date
+        assert_eq!(
+            scheduled(&queue),
+            [("2024-03-15T12:30:00+00:00".to_string(), "01.01.2024jumuah")]
+        );
+    }
+
+    #[test]
+    fn timed_reminders_are_scheduled_once() {
+        let all_dars = [
+            dars(
+                "15.03.2024",
+                vec![
+                    notification("fajr", Some("fajr"), &[]),
+                    notification("maghrib", Some("maghrib"), &[]),
+                    notification("noon", Some("13:00"), &[]),
+                    notification("unknown", Some("sunrise"), &[]),
+                    notification("plain", None, &[]),
+                ],
+            ),
+            dars(
+                "16.03.2024",
+                vec![notification("dhuhr", Some("dhuhr"), &[])],
+            ),
+        ];
+        let queue = Queue::build(&all_dars, &settings(DarsWindow::All), now());
+        assert_eq!(rotation(&queue), ["15.03.2024plain"]);
+        // the fajr of today is already over, reminders are sorted by time
+        assert_eq!(
+            scheduled(&queue),
+            [
+                ("2024-03-15T13:00:00+00:00".to_string(), "15.03.2024noon"),
+                ("2024-03-15T18:15:00+00:00".to_string(), "15.03.2024maghrib"),
+                ("2024-03-16T12:30:00+00:00".to_string(), "16.03.2024dhuhr"),
+            ]
+        );
+
+        let mut istanbul = settings(DarsWindow::All);
+        istanbul.timezone = Some("Europe/Istanbul".to_string());
+        let queue = Queue::build(&all_dars[..1], &istanbul, now());
+        assert_eq!(
+            scheduled(&queue),
+            [
+                ("2024-03-15T10:00:00+00:00".to_string(), "15.03.2024noon"),
+                ("2024-03-15T15:15:00+00:00".to_string(), "15.03.2024maghrib"),
+            ]
+        );
+    }
+
+    #[test]
+    fn skipped_and_learned_ids_are_hidden() {
+        let all_dars = [
+            dars(
+                "14.03.2024",
+                vec![
+                    notification("a", None, &[]),
+                    notification("b", None, &[]),
+                    notification("c", Some("18:00"), &[]),
+                ],
+            ),
+            dars("13.03.2024", vec![notification("a", None, &[])]),
+        ];
+        let mut settings = settings(DarsWindow::All);
+        settings.skip("14.03.2024a");
+        settings.mark_learned("14.03.2024c");
+        let queue = Queue::build(&all_dars, &settings, now());
+        // the same feed id on another date is a different reminder
+        assert_eq!(rotation(&queue), ["14.03.2024b", "13.03.2024a"]);
+        assert!(queue.scheduled().is_empty());
+    }
+
+    #[test]
+    fn rotation_runs_out_once() {
+        let all_dars = [plain("14.03.2024"), plain("13.03.2024")];
+        let mut queue = Queue::build(&all_dars, &settings(DarsWindow::All), now());
+        let ids: Vec<_> = std::iter::from_fn(|| queue.next_notification())
+            .map(|n| n.id)
+            .collect();
+        assert_eq!(ids, ["14.03.20241", "13.03.20241"]);
+        assert!(queue.next_notification().is_none());
+        assert!(!queue.is_empty());
+        assert!(Queue::build(&[], &settings(DarsWindow::All), now()).is_empty());
+    }
+
+    #[test]
+    fn snoozed_reminders_outlive_a_reschedule() {
+        let all_dars = [dars(
+            "15.03.2024",
+            vec![
+                notification("plain", None, &[]),
+                notification("noon", Some("13:00"), &[]),
+            ],
+        )];
+        let settings = settings(DarsWindow::All);
+        let mut queue = Queue::build(&all_dars, &settings, now());
+        let at = now() + chrono::TimeDelta::minutes(10);
+        queue.snooze(notification("15.03.2024plain", None, &[]), at);
+        queue.snooze(notification("15.03.2024plain", None, &[]), at);
+        assert_eq!(queue.snoozed().count(), 1);
+
+        // the clock jumped past noon: the timed reminder is gone, the snoozed one stays
+        let later = "2024-03-15T14:00:00Z".parse().unwrap();
+        queue.reschedule(&all_dars, &settings, later);
+        assert_eq!(
+            scheduled(&queue),
+            [("2024-03-15T09:10:00+00:00".to_string(), "15.03.2024plain")]
+        );
+        let due = queue.take_due(later);
+        assert_eq!(due.len(), 1);
+        assert!(due[0].snoozed);
+        assert!(!queue.cancel_snooze("15.03.2024plain"));
+    }
+
+    #[test]
+    fn snoozed_reminders_outlive_a_rebuild() {
+        let all_dars = [plain("14.03.2024"), plain("13.03.2024")];
+        let settings = settings(DarsWindow::All);
+        let mut queue = Queue::build(&all_dars, &settings, now());
+        let first = queue.next_notification().unwrap();
+        let at = now() + chrono::TimeDelta::minutes(10);
+        queue.snooze(first, at);
+
+        queue.rebuild(&all_dars, &settings, now());
+        assert_eq!(
+            scheduled(&queue),
+            [("2024-03-15T09:10:00+00:00".to_string(), "14.03.20241")]
+        );
+        // the rotation starts over
+        assert_eq!(queue.next_notification().unwrap().id, "14.03.20241");
+    }
+
+    #[test]
+    fn mutes_drop_timed_reminders_and_postpone_snoozed_ones() {
+        let all_dars = [dars(
+            "15.03.2024",
+            vec![notification("noon", Some("13:00"), &[])],
+        )];
+        let mut queue = Queue::build(&all_dars, &settings(DarsWindow::All), now());
+        let at = |time: &str| -> DateTime<Utc> { format!("2024-03-15T{}Z", time).parse().unwrap() };
+        queue.snooze(notification("14.03.2024a", None, &[]), at("12:50:00"));
+
+        // noon is dropped, the snoozed one comes back when the mute ends
+        let until = at("14:00:00");
+        assert!(queue
+            .due_notifications(at("13:00:00"), Some(until))
+            .is_empty());
+        assert_eq!(
+            scheduled(&queue),
+            [("2024-03-15T14:00:00+00:00".to_string(), "14.03.2024a")]
+        );
+
+        let shown = queue.due_notifications(until, None);
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].id, "14.03.2024a");
+        assert!(queue.scheduled().is_empty());
+    }
+
+    #[test]
+    fn only_snoozed_reminders_are_shown_late() {
+        let all_dars = [dars(
+            "15.03.2024",
+            vec![
+                notification("noon", Some("13:00"), &[]),
+                notification("later", Some("13:30"), &[]),
+            ],
+        )];
+        let mut queue = Queue::build(&all_dars, &settings(DarsWindow::All), now());
+        let at = |time: &str| -> DateTime<Utc> { format!("2024-03-15T{}Z", time).parse().unwrap() };
+        queue.snooze(notification("14.03.2024a", None, &[]), at("12:00:00"));
+
+        // woken up at 13:33: noon was missed, later is late but not missed
+        let shown: Vec<_> = queue
+            .due_notifications(at("13:33:00"), None)
+            .into_iter()
+            .map(|n| n.id)
+            .collect();
+        assert_eq!(shown, ["14.03.2024a", "15.03.2024later"]);
+        assert!(queue.scheduled().is_empty());
+    }
+}
@@ -0,0 +1,37 @@
+// Per-source secrets in the system keyring (Keychain, Credential Manager or the Secret Service),
+// one JSON entry per source url so settings.json never holds a token or a password.
+use iustadji_domain::SourceSecrets;
+use keyring::Entry;
+
+const SERVICE: &str = "iustadji";
+
+pub fn load(url: &str) -> Result<SourceSecrets, String> {
+    match entry(url)?.get_password() {
+        Ok(json) => serde_json::from_str(&json)
+            .map_err(|err| format!("Invalid secrets for {}: {}", url, err)),
+        Err(keyring::Error::NoEntry) => Ok(SourceSecrets::default()),
+        Err(err) => Err(format!("Failed to read secrets for {}: {}", url, err)),
+    }
+}
+
+// empty secrets remove the entry
+pub fn save(url: &str, secrets: &SourceSecrets) -> Result<(), String> {
+    if *secrets == SourceSecrets::default() {
+        return delete(url);
+    }
+    let json = serde_json::to_string(secrets).map_err(|err| err.to_string())?;
+    entry(url)?
+        .set_password(&json)
+        .map_err(|err| format!("Failed to store secrets for {}: {}", url, err))
+}
+
+pub fn delete(url: &str) -> Result<(), String> {
+    match entry(url)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(err) => Err(format!("Failed to remove secrets for {}: {}", url, err)),
+    }
+}
+
+fn entry(url: &str) -> Result<Entry, String> {
+    Entry::new(SERVICE, url).map_err(|err| format!("Keyring unavailable: {}", err))
+}
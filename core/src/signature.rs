@@ -0,0 +1,83 @@
+// Detached Ed25519 signatures of feeds. The publisher signs the exact bytes of the feed and
+// serves the 64 signature bytes, raw or in base64, next to it as `<feed>.sig`.
+use base64::prelude::{Engine, BASE64_STANDARD};
+use reqwest::Url;
+use ring::signature::{UnparsedPublicKey, ED25519};
+
+pub fn signature_url(url: &str) -> Result<Url, String> {
+    let mut url = Url::parse(url).map_err(|err| format!("Invalid url {}: {}", url, err))?;
+    let path = format!("{}.sig", url.path());
+    url.set_path(&path);
+    Ok(url)
+}
+
+pub fn verify(public_key: &str, body: &[u8], signature: &[u8]) -> Result<(), String> {
+    let public_key = BASE64_STANDARD
+        .decode(public_key.trim())
+        .map_err(|err| format!("Invalid public key: {}", err))?;
+    let signature = match signature.len() {
+        64 => signature.to_vec(),
+        _ => BASE64_STANDARD
+            .decode(signature.trim_ascii())
+            .map_err(|err| format!("Invalid signature: {}", err))?,
+    };
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(body, &signature)
+        .map_err(|_| {
+            "The signature doesn't match the pinned key, the feed may be tampered with".to_string()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    const FEED: &[u8] = br#"[{"date":"16.03.2024","notifications":[]}]"#;
+
+    // a fresh publisher key, base64 as pinned in a source
+    fn key_pair() -> (Ed25519KeyPair, String) {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let public_key = BASE64_STANDARD.encode(key_pair.public_key());
+        (key_pair, public_key)
+    }
+
+    #[test]
+    fn raw_and_base64_signatures_verify() {
+        let (key_pair, public_key) = key_pair();
+        let signature = key_pair.sign(FEED);
+        assert_eq!(verify(&public_key, FEED, signature.as_ref()), Ok(()));
+        let encoded = BASE64_STANDARD.encode(signature) + "\n";
+        assert_eq!(verify(&public_key, FEED, encoded.as_bytes()), Ok(()));
+    }
+
+    #[test]
+    fn tampered_feeds_are_rejected() {
+        let (key_pair, public_key) = key_pair();
+        let signature = key_pair.sign(FEED);
+        let tampered = std::str::from_utf8(FEED).unwrap().replace("16.03", "17.03");
+        assert!(verify(&public_key, tampered.as_bytes(), signature.as_ref()).is_err());
+
+        let (_, other_key) = self::key_pair();
+        assert!(verify(&other_key, FEED, signature.as_ref()).is_err());
+    }
+
+    #[test]
+    fn malformed_keys_and_signatures_are_reported() {
+        let (key_pair, public_key) = key_pair();
+        let signature = key_pair.sign(FEED);
+        let err = verify("not base64!", FEED, signature.as_ref()).unwrap_err();
+        assert!(err.starts_with("Invalid public key"), "{}", err);
+        let err = verify(&public_key, FEED, b"too short").unwrap_err();
+        assert!(err.starts_with("Invalid signature"), "{}", err);
+    }
+
+    #[test]
+    fn signatures_sit_next_to_the_feed() {
+        let url = signature_url("https://example.org/dars.json?v=2").unwrap();
+        assert_eq!(url.as_str(), "https://example.org/dars.json.sig?v=2");
+        assert!(signature_url("dars.json").is_err());
+    }
+}
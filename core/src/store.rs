@@ -0,0 +1,145 @@
+use crate::paths::Paths;
+use iustadji_domain::Settings;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::{fs, io, process};
+
+// tells apart the temporary files of concurrent saves within one process
+static SAVES: AtomicU64 = AtomicU64::new(0);
+
+pub fn load_settings(paths: &Paths) -> Result<Settings, String> {
+    match read_settings(paths)? {
+        Some(settings) => Ok(settings),
+        None => {
+            // If the file doesn't exist, create it with default settings
+            let settings = Settings::default();
+            save_settings(paths, &settings)?;
+            Ok(settings)
+        }
+    }
+}
+
+// `None` without a settings file, which is left uncreated; it holds a one element array, kept
+// that way for files written by older versions
+pub fn read_settings(paths: &Paths) -> Result<Option<Settings>, String> {
+    let file_content = match fs::read_to_string(&paths.settings) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(format!("Failed to read settings file: {}", err)),
+    };
+    serde_json::from_str::<Vec<Settings>>(&file_content)
+        .map_err(|err| format!("Failed to parse settings file: {}", err))?
+        .into_iter()
+        .next()
+        .map(Some)
+        .ok_or_else(|| "Settings file is empty".to_string())
+}
+
+pub fn save_settings(paths: &Paths, settings: &Settings) -> Result<(), String> {
+    settings.validate()?;
+    let settings_json = serde_json::to_string_pretty(&vec![settings])
+        .map_err(|err| format!("Failed to serialize settings to JSON: {}", err))?;
+    if let Some(dir) = paths.settings.parent() {
+        fs::create_dir_all(dir).map_err(|err| format!("Failed to create settings dir: {}", err))?;
+    }
+    // the app, the cli and the daemon all read it at any time, so it is replaced in one step
+    // instead of being truncated and written over
+    let tmp = paths.settings.with_extension(format!(
+        "json.{}-{}.tmp",
+        process::id(),
+        SAVES.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&tmp, settings_json)
+        .and_then(|()| fs::rename(&tmp, &paths.settings))
+        .map_err(|err| {
+            let _ = fs::remove_file(&tmp);
+            format!("Failed to write settings file: {}", err)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use tempfile::TempDir;
+
+    fn paths() -> (TempDir, Paths) {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::in_dir(dir.path());
+        (dir, paths)
+    }
+
+    #[test]
+    fn reading_leaves_a_missing_file_missing() {
+        let (_dir, paths) = paths();
+        assert_eq!(read_settings(&paths).map(|s| s.is_none()), Ok(true));
+        assert!(!paths.settings.exists());
+        let settings = load_settings(&paths).unwrap();
+        assert_eq!(settings.data_url, Settings::default().data_url);
+        assert!(paths.settings.exists());
+    }
+
+    #[test]
+    fn saved_settings_read_back() {
+        let (dir, paths) = paths();
+        let settings = Settings {
+            interval: 25,
+            timezone: Some("Asia/Dhaka".to_string()),
+            ..Settings::default()
+        };
+        save_settings(&paths, &settings).unwrap();
+        let read = read_settings(&paths).unwrap().unwrap();
+        assert_eq!(read.interval, 25);
+        assert_eq!(read.timezone.as_deref(), Some("Asia/Dhaka"));
+        // nothing but the file itself is left behind
+        let files: Vec<_> = fs::read_dir(dir.path()).unwrap().collect();
+        assert_eq!(files.len(), 1);
+    }
+
+    #[test]
+    fn invalid_settings_are_not_saved() {
+        let (_dir, paths) = paths();
+        save_settings(&paths, &Settings::default()).unwrap();
+        let invalid = Settings {
+            interval: 0,
+            ..Settings::default()
+        };
+        assert!(save_settings(&paths, &invalid).is_err());
+        assert_eq!(read_settings(&paths).unwrap().unwrap().interval, 10);
+    }
+
+    #[test]
+    fn broken_files_are_reported() {
+        let (_dir, paths) = paths();
+        fs::write(&paths.settings, "[{\"data_url\":").unwrap();
+        assert!(read_settings(&paths).is_err());
+        assert!(load_settings(&paths).is_err());
+        fs::write(&paths.settings, "[]").unwrap();
+        assert_eq!(
+            read_settings(&paths).map(|_| ()),
+            Err("Settings file is empty".to_string())
+        );
+    }
+
+    #[test]
+    fn readers_never_see_half_a_file() {
+        let (_dir, paths) = paths();
+        save_settings(&paths, &Settings::default()).unwrap();
+        let writer = {
+            let paths = paths.clone();
+            thread::spawn(move || {
+                for interval in 1..200 {
+                    let settings = Settings {
+                        interval,
+                        ..Settings::default()
+                    };
+                    save_settings(&paths, &settings).unwrap();
+                }
+            })
+        };
+        while !writer.is_finished() {
+            assert!(read_settings(&paths).unwrap().is_some());
+        }
+        writer.join().unwrap();
+        assert_eq!(read_settings(&paths).unwrap().unwrap().interval, 199);
+    }
+}
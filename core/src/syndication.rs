@@ -0,0 +1,255 @@
+// RSS, Atom and JSON Feed sources next to the native list of dars. Every item becomes a
+// notification on the day it was published, with an id derived from its guid so that learned,
+// skipped and snoozed items stay so across fetches.
+use feed_rs::model::{Entry, Text};
+use feed_rs::parser::Builder;
+use iustadji_domain::feed::parse_feed;
+use iustadji_domain::{Dars, DarsDate, NotificationData, Zone};
+use ring::digest::{digest, SHA256};
+use std::collections::BTreeMap;
+
+// the native feed is a JSON array, a JSON Feed an object and RSS and Atom are XML
+pub fn parse(body: &[u8], zone: Zone) -> Result<(Vec<Dars>, Vec<String>), String> {
+    let body = body.strip_prefix("\u{feff}".as_bytes()).unwrap_or(body);
+    match body.iter().find(|b| !b.is_ascii_whitespace()) {
+        Some(b'[') => {
+            let body = std::str::from_utf8(body).map_err(|err| format!("Not UTF-8: {}", err))?;
+            parse_feed(body).map_err(|err| format!("Invalid JSON: {}", err))
+        }
+        Some(b'<' | b'{') => parse_syndication(body, zone),
+        _ => Err("Not a dars list, RSS, Atom or JSON Feed".to_string()),
+    }
+}
+
+fn parse_syndication(body: &[u8], zone: Zone) -> Result<(Vec<Dars>, Vec<String>), String> {
+    // the default falls back to a random id for items without guid or link
+    let parser = Builder::new()
+        .id_generator(|links, title, _| match links.first() {
+            Some(link) => link.href.clone(),
+            None => title
+                .as_ref()
+                .map(|t| t.content.clone())
+                .unwrap_or_default(),
+        })
+        .build();
+    let feed = parser.parse(body).map_err(|err| err.to_string())?;
+
+    let mut by_date: BTreeMap<DarsDate, Vec<NotificationData>> = BTreeMap::new();
+    let mut errors = vec![];
+    for (i, entry) in feed.entries.into_iter().enumerate() {
+        match notification(entry, zone) {
+            Ok((date, notification)) => by_date.entry(date).or_default().push(notification),
+            Err(err) => errors.push(format!("item {}: {}", i, err)),
+        }
+    }
+    let all_dars = by_date
+        .into_iter()
+        .rev()
+        .map(|(date, notifications)| Dars {
+            date,
+            notifications,
+        })
+        .collect();
+    Ok((all_dars, errors))
+}
+
+fn notification(entry: Entry, zone: Zone) -> Result<(DarsDate, NotificationData), String> {
+    if entry.id.trim().is_empty() {
+        return Err("no guid, link or title".to_string());
+    }
+    let published = entry
+        .published
+        .or(entry.updated)
+        .ok_or_else(|| format!("{}: no date", entry.id))?;
+    let title = entry.title.as_ref().map(plain).unwrap_or_default();
+    let description = entry
+        .summary
+        .as_ref()
+        .map(plain)
+        .or_else(|| {
+            entry
+                .content
+                .and_then(|c| c.body)
+                .map(|body| strip_html(&body))
+        })
+        .unwrap_or_default();
+    if title.is_empty() && description.is_empty() {
+        return Err(format!("{}: no title nor text", entry.id));
+    }
+    let notification = NotificationData {
+        id: id(&entry.id),
+        title,
+        description,
+        time: None,
+        active: vec![],
+    };
+    Ok((zone.local(published).date().into(), notification))
+}
+
+// the same guid always gives the same id, short and safe to put in a link
+fn id(guid: &str) -> String {
+    let hash = digest(&SHA256, guid.trim().as_bytes());
+    let hex: String = hash.as_ref()[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("feed-{}", hex)
+}
+
+fn plain(text: &Text) -> String {
+    match text.content_type.subty().as_str() {
+        "html" | "xhtml+xml" => strip_html(&text.content),
+        _ => collapse(&text.content),
+    }
+}
+
+// notifications are plain text: tags are dropped and the common entities decoded
+fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+    collapse(&text)
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSS: &str = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel><title>Dars</title>
+  <item>
+    <guid>lesson-1</guid>
+    <title>Patience</title>
+    <description>&lt;p&gt;Be &lt;b&gt;patient&lt;/b&gt; &amp;amp; steadfast&lt;/p&gt;</description>
+    <pubDate>Sat, 16 Mar 2024 22:30:00 +0000</pubDate>
+  </item>
+  <item>
+    <guid>lesson-2</guid>
+    <title>Gratitude</title>
+    <pubDate>Sat, 16 Mar 2024 08:00:00 +0000</pubDate>
+  </item>
+  <item><guid>lesson-3</guid><title>Undated</title></item>
+</channel></rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom"><title>Dars</title><id>urn:dars</id>
+  <updated>2024-03-17T10:00:00Z</updated>
+  <entry>
+    <id>urn:lesson-1</id>
+    <title>Sincerity</title>
+    <updated>2024-03-17T10:00:00Z</updated>
+    <content type="html">&lt;p&gt;Deeds are by intentions&lt;/p&gt;</content>
+  </entry>
+</feed>"#;
+
+    const JSON_FEED: &str = r#"{
+  "version": "https://jsonfeed.org/version/1.1",
+  "title": "Dars",
+  "items": [
+    {"id": "1", "title": "Mercy", "content_text": "Show mercy", "date_published": "2024-03-18T06:00:00Z"}
+  ]
+}"#;
+
+    fn utc() -> Zone {
+        Zone::parse(Some("UTC")).unwrap()
+    }
+
+    fn date(date: &str) -> DarsDate {
+        DarsDate::parse(date).unwrap()
+    }
+
+    #[test]
+    fn rss_items_are_grouped_by_day() {
+        let (all_dars, errors) = parse(RSS.as_bytes(), utc()).unwrap();
+        assert_eq!(all_dars.len(), 1);
+        assert_eq!(all_dars[0].date, date("16.03.2024"));
+        let notifications = &all_dars[0].notifications;
+        assert_eq!(notifications.len(), 2);
+        assert_eq!(notifications[0].title, "Patience");
+        assert_eq!(notifications[0].description, "Be patient & steadfast");
+        assert_eq!(notifications[1].description, "");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("item 2"), "{}", errors[0]);
+    }
+
+    #[test]
+    fn days_follow_the_zone() {
+        let istanbul = Zone::parse(Some("Europe/Istanbul")).unwrap();
+        let (all_dars, _) = parse(RSS.as_bytes(), istanbul).unwrap();
+        let dates: Vec<_> = all_dars.iter().map(|d| d.date).collect();
+        assert_eq!(dates, [date("17.03.2024"), date("16.03.2024")]);
+    }
+
+    #[test]
+    fn ids_are_stable_per_guid() {
+        let (first, _) = parse(RSS.as_bytes(), utc()).unwrap();
+        let (second, _) = parse(RSS.as_bytes(), utc()).unwrap();
+        let ids = |all_dars: &[Dars]| -> Vec<String> {
+            all_dars[0]
+                .notifications
+                .iter()
+                .map(|n| n.id.clone())
+                .collect()
+        };
+        assert_eq!(ids(&first), ids(&second));
+        assert_ne!(ids(&first)[0], ids(&first)[1]);
+        assert!(ids(&first)[0].starts_with("feed-"));
+        assert_eq!(ids(&first)[0], id(" lesson-1 "));
+    }
+
+    #[test]
+    fn atom_and_json_feed_are_read() {
+        let (atom, errors) = parse(ATOM.as_bytes(), utc()).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(atom[0].date, date("17.03.2024"));
+        assert_eq!(atom[0].notifications[0].title, "Sincerity");
+        assert_eq!(
+            atom[0].notifications[0].description,
+            "Deeds are by intentions"
+        );
+
+        let (json, errors) = parse(JSON_FEED.as_bytes(), utc()).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(json[0].date, date("18.03.2024"));
+        assert_eq!(json[0].notifications[0].description, "Show mercy");
+    }
+
+    #[test]
+    fn native_lists_pass_through() {
+        let body = "\u{feff} [{\"date\":\"16.03.2024\",\"notifications\":\
+                    [{\"id\":\"1\",\"title\":\"t\",\"description\":\"d\"}]},{\"date\":\"bad\"}]";
+        let (all_dars, errors) = parse(body.as_bytes(), utc()).unwrap();
+        assert_eq!(all_dars.len(), 1);
+        assert_eq!(all_dars[0].notifications[0].id, "1");
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn anything_else_is_refused() {
+        assert!(parse(b"", utc()).is_err());
+        assert!(parse(b"dars", utc()).is_err());
+        assert!(parse(b"<html><body>not a feed</body></html>", utc()).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use crate::hijri;
+use chrono::format::StrftimeItems;
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{self, Write};
+
+// how dates are written back to the feed, settings and notification ids
+const CANONICAL_FORMAT: &str = "%d.%m.%Y";
+pub const DEFAULT_DISPLAY_FORMAT: &str = "%a, %-d %b %Y";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DarsDate(NaiveDate);
+
+impl DarsDate {
+    // accepts "dd.mm.yyyy", ISO 8601 ("yyyy-mm-dd" or a full timestamp) and hijri "dd.mm.yyyy AH"
+    pub fn parse(date: &str) -> Option<DarsDate> {
+        let date = date.trim();
+        if let Ok(parsed) = NaiveDate::parse_from_str(date, CANONICAL_FORMAT) {
+            return Some(DarsDate(parsed));
+        }
+        if let Ok(parsed) = DateTime::parse_from_rfc3339(date) {
+            return Some(DarsDate(parsed.date_naive()));
+        }
+        if let Ok((parsed, rest)) = NaiveDate::parse_and_remainder(date, "%Y-%m-%d") {
+            if rest.is_empty() || rest.starts_with('T') || rest.starts_with(' ') {
+                return Some(DarsDate(parsed));
+            }
+        }
+        hijri::parse(date).map(DarsDate)
+    }
+
+    pub fn naive(self) -> NaiveDate {
+        self.0
+    }
+
+    // falls back to the default format instead of panicking on a bad pattern or on one a date
+    // can't fill in, like %H or %z
+    pub fn display(self, format: &str) -> String {
+        self.try_display(format)
+            .unwrap_or_else(|_| self.0.format(DEFAULT_DISPLAY_FORMAT).to_string())
+    }
+
+    fn try_display(self, format: &str) -> Result<String, fmt::Error> {
+        let mut text = String::new();
+        write!(
+            text,
+            "{}",
+            self.0.format_with_items(StrftimeItems::new(format))
+        )?;
+        Ok(text)
+    }
+}
+
+// the formats `display` shows as given, every other one falls back to the default
+pub fn check_display_format(format: &str) -> Result<(), String> {
+    DarsDate(NaiveDate::default())
+        .try_display(format)
+        .map(drop)
+        .map_err(|_| format!("Invalid date format: {}", format))
+}
+
+impl From<NaiveDate> for DarsDate {
+    fn from(date: NaiveDate) -> Self {
+        DarsDate(date)
+    }
+}
+
+impl fmt::Display for DarsDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.format(CANONICAL_FORMAT))
+    }
+}
+
+impl Serialize for DarsDate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DarsDate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let date = String::deserialize(deserializer)?;
+        DarsDate::parse(&date)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid date: {}", date)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ymd(year: i32, month: u32, day: u32) -> Option<DarsDate> {
+        NaiveDate::from_ymd_opt(year, month, day).map(DarsDate)
+    }
+
+    #[test]
+    fn every_accepted_spelling_parses() {
+        let date = ymd(2024, 3, 16);
+        assert_eq!(DarsDate::parse("16.03.2024"), date);
+        assert_eq!(DarsDate::parse(" 16.03.2024\n"), date);
+        assert_eq!(DarsDate::parse("2024-03-16"), date);
+        assert_eq!(DarsDate::parse("2024-03-16T10:00:00"), date);
+        assert_eq!(DarsDate::parse("2024-03-16 10:00"), date);
+        assert_eq!(DarsDate::parse("2024-03-16T23:30:00-05:00"), date);
+        assert_eq!(DarsDate::parse("06.09.1445 AH"), date);
+        assert_eq!(DarsDate::parse("06.09.1445H"), date);
+    }
+
+    #[test]
+    fn malformed_dates_are_refused() {
+        for date in [
+            "",
+            "16/03/2024",
+            "31.02.2024",
+            "2024-03-16x",
+            "2024-03",
+            "31.09.1445 AH",
+            "16.03.2024 AD",
+        ] {
+            assert_eq!(DarsDate::parse(date), None, "{}", date);
+        }
+    }
+
+    #[test]
+    fn written_back_canonically() {
+        let date = DarsDate::parse("2024-03-06").unwrap();
+        assert_eq!(date.to_string(), "06.03.2024");
+        assert_eq!(serde_json::to_string(&date).unwrap(), "\"06.03.2024\"");
+        let read: DarsDate = serde_json::from_str("\"06.03.2024\"").unwrap();
+        assert_eq!(read, date);
+        assert!(serde_json::from_str::<DarsDate>("\"someday\"").is_err());
+    }
+
+    #[test]
+    fn bad_display_formats_fall_back() {
+        let date = ymd(2024, 3, 16).unwrap();
+        assert_eq!(date.display("%Y/%m/%d"), "2024/03/16");
+        assert_eq!(date.display("%Q"), "Sat, 16 Mar 2024");
+        // a date has no time nor offset to show
+        assert_eq!(date.display("%H:%M"), "Sat, 16 Mar 2024");
+        assert_eq!(date.display("%d.%m.%Y %z"), "Sat, 16 Mar 2024");
+    }
+
+    #[test]
+    fn only_formats_shown_as_given_pass() {
+        assert_eq!(check_display_format("%-d %B %Y"), Ok(()));
+        assert_eq!(check_display_format("%A, %x"), Ok(()));
+        assert!(check_display_format("%Q").is_err());
+        assert!(check_display_format("%H:%M").is_err());
+        assert!(check_display_format("%z").is_err());
+    }
+}
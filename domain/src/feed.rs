@@ -0,0 +1,15 @@
+use crate::model::Dars;
+
+// one malformed entry shouldn't throw away the rest of the feed, skipped entries are returned as errors
+pub fn parse_feed(body: &str) -> Result<(Vec<Dars>, Vec<String>), serde_json::Error> {
+    let entries = serde_json::from_str::<Vec<serde_json::Value>>(body)?;
+    let mut all_dars = vec![];
+    let mut errors = vec![];
+    for (i, entry) in entries.into_iter().enumerate() {
+        match serde_json::from_value::<Dars>(entry) {
+            Ok(dars) => all_dars.push(dars),
+            Err(err) => errors.push(format!("entry {}: {}", i, err)),
+        }
+    }
+    Ok((all_dars, errors))
+}
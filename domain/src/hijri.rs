@@ -0,0 +1,205 @@
+use chrono::{Days, NaiveDate};
+use std::fmt;
+
+// tabular (arithmetical) islamic calendar, civil epoch 1 Muharram 1 AH = 19.07.622
+const EPOCH: NaiveDate = match NaiveDate::from_ymd_opt(622, 7, 19) {
+    Some(date) => date,
+    None => panic!("invalid hijri epoch"),
+};
+
+pub const MONTH_NAMES: [&str; 12] = [
+    "Muharram",
+    "Safar",
+    "Rabi al-Awwal",
+    "Rabi al-Thani",
+    "Jumada al-Ula",
+    "Jumada al-Thani",
+    "Rajab",
+    "Shaban",
+    "Ramadan",
+    "Shawwal",
+    "Dhul Qadah",
+    "Dhul Hijjah",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HijriDate {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl HijriDate {
+    pub fn from_gregorian(date: NaiveDate) -> Option<HijriDate> {
+        let n = (date - EPOCH).num_days();
+        if n < 0 {
+            return None;
+        }
+        let year = ((30 * n + 10646) / 10631) as i32;
+        let month = (ceil_div(2 * (n - 29 - days_from_epoch(year, 1, 1)), 59) + 1).min(12) as u32;
+        let day = (n - days_from_epoch(year, month, 1) + 1) as u32;
+        Some(HijriDate { year, month, day })
+    }
+
+    pub fn to_gregorian(self) -> Option<NaiveDate> {
+        if self.year < 1
+            || !(1..=12).contains(&self.month)
+            || self.day < 1
+            || self.day > month_length(self.year, self.month)
+        {
+            return None;
+        }
+        EPOCH.checked_add_days(Days::new(
+            days_from_epoch(self.year, self.month, self.day) as u64
+        ))
+    }
+}
+
+impl fmt::Display for HijriDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let month = MONTH_NAMES[(self.month as usize - 1) % 12];
+        write!(f, "{} {} {} AH", self.day, month, self.year)
+    }
+}
+
+// matches "ramadan", "Dhul Hijjah", "dhul_hijjah" and similar spellings
+pub fn month_from_name(name: &str) -> Option<u32> {
+    let normalize = |s: &str| {
+        s.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase()
+    };
+    let name = normalize(name);
+    MONTH_NAMES
+        .iter()
+        .position(|month| normalize(month) == name)
+        .map(|i| i as u32 + 1)
+}
+
+// feed dates written as "dd.mm.yyyy AH" or "dd.mm.yyyyH"
+pub fn parse(date: &str) -> Option<NaiveDate> {
+    let date = date.trim();
+    let date = date
+        .strip_suffix("AH")
+        .or_else(|| date.strip_suffix('H'))?
+        .trim_end();
+    let mut parts = date.split('.');
+    let day = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let year = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    HijriDate { year, month, day }.to_gregorian()
+}
+
+pub fn month_length(year: i32, month: u32) -> u32 {
+    let next = if month == 12 {
+        days_from_epoch(year + 1, 1, 1)
+    } else {
+        days_from_epoch(year, month + 1, 1)
+    };
+    (next - days_from_epoch(year, month, 1)) as u32
+}
+
+// first and last gregorian day of the hijri month containing `date`
+pub fn month_range(date: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+    let hijri = HijriDate::from_gregorian(date)?;
+    let start = HijriDate { day: 1, ..hijri }.to_gregorian()?;
+    let end = HijriDate {
+        day: month_length(hijri.year, hijri.month),
+        ..hijri
+    }
+    .to_gregorian()?;
+    Some((start, end))
+}
+
+fn days_from_epoch(year: i32, month: u32, day: u32) -> i64 {
+    let (year, month, day) = (year as i64, month as i64, day as i64);
+    (day - 1) + (59 * (month - 1) + 1) / 2 + (year - 1) * 354 + (3 + 11 * year) / 30
+}
+
+fn ceil_div(a: i64, b: i64) -> i64 {
+    -((-a).div_euclid(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ymd(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn hijri(year: i32, month: u32, day: u32) -> HijriDate {
+        HijriDate { year, month, day }
+    }
+
+    #[test]
+    fn known_dates_convert() {
+        let known = [
+            (ymd(622, 7, 19), hijri(1, 1, 1)),
+            (ymd(2024, 3, 11), hijri(1445, 9, 1)),
+            (ymd(2024, 7, 7), hijri(1445, 12, 30)),
+            (ymd(2024, 7, 8), hijri(1446, 1, 1)),
+        ];
+        for (gregorian, hijri) in known {
+            assert_eq!(HijriDate::from_gregorian(gregorian), Some(hijri));
+            assert_eq!(hijri.to_gregorian(), Some(gregorian));
+        }
+        assert_eq!(HijriDate::from_gregorian(ymd(622, 7, 18)), None);
+    }
+
+    #[test]
+    fn round_trips_through_gregorian() {
+        let mut date = ymd(2020, 1, 1);
+        while date < ymd(2040, 1, 1) {
+            let hijri = HijriDate::from_gregorian(date).unwrap();
+            assert!(
+                hijri.day <= month_length(hijri.year, hijri.month),
+                "{}",
+                date
+            );
+            assert_eq!(hijri.to_gregorian(), Some(date));
+            date = date.succ_opt().unwrap();
+        }
+    }
+
+    #[test]
+    fn impossible_dates_are_refused() {
+        assert_eq!(hijri(0, 1, 1).to_gregorian(), None);
+        assert_eq!(hijri(1445, 13, 1).to_gregorian(), None);
+        assert_eq!(hijri(1445, 9, 0).to_gregorian(), None);
+        // Ramadan 1445 has 30 days, Shawwal 29
+        assert_eq!(hijri(1445, 10, 30).to_gregorian(), None);
+        assert_eq!(month_length(1445, 9), 30);
+        assert_eq!(month_length(1445, 10), 29);
+    }
+
+    #[test]
+    fn month_names_are_forgiving() {
+        assert_eq!(month_from_name("ramadan"), Some(9));
+        assert_eq!(month_from_name("Dhul Hijjah"), Some(12));
+        assert_eq!(month_from_name("dhul_hijjah"), Some(12));
+        assert_eq!(month_from_name("rabi-al-awwal"), Some(3));
+        assert_eq!(month_from_name("march"), None);
+        assert_eq!(hijri(1445, 9, 5).to_string(), "5 Ramadan 1445 AH");
+    }
+
+    #[test]
+    fn feed_dates_and_month_ranges() {
+        assert_eq!(parse("01.09.1445 AH"), Some(ymd(2024, 3, 11)));
+        assert_eq!(parse("1.9.1445H"), Some(ymd(2024, 3, 11)));
+        assert_eq!(parse("01.09.1445"), None);
+        assert_eq!(parse("01.09 AH"), None);
+        assert_eq!(
+            month_range(ymd(2024, 3, 15)),
+            Some((ymd(2024, 3, 11), ymd(2024, 4, 9)))
+        );
+        assert_eq!(
+            month_range(ymd(2024, 3, 10)),
+            Some((ymd(2024, 2, 11), ymd(2024, 3, 10)))
+        );
+    }
+}
@@ -0,0 +1,235 @@
+// Contracts for the tauri commands and events. The frontend client and the backend handlers are
+// both written against these, so a renamed field or a changed return type fails to compile on one
+// side. A handler takes the whole `Args` struct as its `args` parameter, the client sends `{ args }`.
+use crate::model::{Dars, NotificationData, Settings, SourceSecrets};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+pub trait Command {
+    const NAME: &'static str;
+    // the `args` parameter of the handler, left out by handlers of commands without any
+    type Args: Serialize + DeserializeOwned;
+    type Output: Serialize + DeserializeOwned;
+}
+
+// what a handler for `C` takes, as its `args` parameter
+pub type Args<C> = <C as Command>::Args;
+
+// what a handler for `C` returns, errors reach the frontend as the rejection message
+pub type IpcResult<C> = Result<<C as Command>::Output, String>;
+
+pub struct GetDars;
+
+impl Command for GetDars {
+    const NAME: &'static str = "get_dars";
+    type Args = ();
+    type Output = Vec<Dars>;
+}
+
+pub struct GetSettings;
+
+impl Command for GetSettings {
+    const NAME: &'static str = "get_settings";
+    type Args = ();
+    type Output = Settings;
+}
+
+pub struct SetSettings;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetSettingsArgs {
+    pub settings: Settings,
+}
+
+impl Command for SetSettings {
+    const NAME: &'static str = "set_settings";
+    type Args = SetSettingsArgs;
+    type Output = ();
+}
+
+// mutes until the given time or unmutes with `None`, without restarting the rotation
+pub struct SetMute;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetMuteArgs {
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl Command for SetMute {
+    const NAME: &'static str = "set_mute";
+    type Args = SetMuteArgs;
+    type Output = ();
+}
+
+// a reminder put back by the user, shown again at `at`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snoozed {
+    pub at: DateTime<Utc>,
+    pub notification: NotificationData,
+}
+
+pub struct GetSnoozed;
+
+impl Command for GetSnoozed {
+    const NAME: &'static str = "get_snoozed";
+    type Args = ();
+    type Output = Vec<Snoozed>;
+}
+
+// the notification carries its composite id, snoozing it again moves it
+pub struct Snooze;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnoozeArgs {
+    pub notification: NotificationData,
+    pub minutes: u64,
+}
+
+impl Command for Snooze {
+    const NAME: &'static str = "snooze";
+    type Args = SnoozeArgs;
+    type Output = ();
+}
+
+pub struct CancelSnooze;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CancelSnoozeArgs {
+    pub id: String,
+}
+
+impl Command for CancelSnooze {
+    const NAME: &'static str = "cancel_snooze";
+    type Args = CancelSnoozeArgs;
+    type Output = ();
+}
+
+// stops every feed fetch in progress, GetDars waiting on one fails
+pub struct CancelFetch;
+
+impl Command for CancelFetch {
+    const NAME: &'static str = "cancel_fetch";
+    type Args = ();
+    type Output = ();
+}
+
+// the last status of every configured source, for a window opened after it was reported, e.g.
+// during the fetch at launch
+pub struct GetFetchStatus;
+
+impl Command for GetFetchStatus {
+    const NAME: &'static str = "get_fetch_status";
+    type Args = ();
+    type Output = Vec<FetchStatus>;
+}
+
+// what a fetch of the source at `url` is doing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FetchStatus {
+    pub url: String,
+    pub state: FetchState,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FetchState {
+    Fetching { attempt: u32, tries: u32 },
+    Retrying { error: String, seconds: u64 },
+    Done { count: usize },
+    Failed { error: String },
+    // the signature doesn't match the pinned key, nothing of the feed was taken
+    Rejected { error: String },
+    Cancelled,
+}
+
+// tokens, passwords and header values of a source, from the system keyring
+pub struct GetSourceSecrets;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetSourceSecretsArgs {
+    pub url: String,
+}
+
+impl Command for GetSourceSecrets {
+    const NAME: &'static str = "get_source_secrets";
+    type Args = GetSourceSecretsArgs;
+    type Output = SourceSecrets;
+}
+
+// replaces all of them, empty secrets remove the keyring entry
+pub struct SetSourceSecrets;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SetSourceSecretsArgs {
+    pub url: String,
+    pub secrets: SourceSecrets,
+}
+
+impl Command for SetSourceSecrets {
+    const NAME: &'static str = "set_source_secrets";
+    type Args = SetSourceSecretsArgs;
+    type Output = ();
+}
+
+// a route the backend wants the window to show, handed out once
+pub struct TakeRoute;
+
+impl Command for TakeRoute {
+    const NAME: &'static str = "take_route";
+    type Args = ();
+    type Output = Option<String>;
+}
+
+// emitted by the backend to every window
+pub trait Event {
+    const NAME: &'static str;
+    type Payload: Serialize + DeserializeOwned + Clone;
+}
+
+// a route is waiting in TakeRoute, emitted after the window was shown
+pub struct Navigate;
+
+impl Event for Navigate {
+    const NAME: &'static str = "navigate";
+    type Payload = ();
+}
+
+// settings were changed outside the settings page, e.g. by a subscribe link or a mute from the tray
+pub struct SettingsChanged;
+
+impl Event for SettingsChanged {
+    const NAME: &'static str = "settings_changed";
+    type Payload = Settings;
+}
+
+// a reminder was snoozed, cancelled or came due again
+pub struct SnoozedChanged;
+
+impl Event for SnoozedChanged {
+    const NAME: &'static str = "snoozed_changed";
+    type Payload = Vec<Snoozed>;
+}
+
+// a source started loading, is waiting for a retry or finished
+pub struct FetchStatusChanged;
+
+impl Event for FetchStatusChanged {
+    const NAME: &'static str = "fetch_status";
+    type Payload = FetchStatus;
+}
+
+// str equality usable in const assertions on the handler names
+pub const fn same_name(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
@@ -0,0 +1,14 @@
+// Types shared across the IPC boundary, compiled for both the tauri backend and the wasm frontend.
+pub mod date;
+pub mod feed;
+pub mod hijri;
+pub mod ipc;
+pub mod link;
+pub mod model;
+pub mod rules;
+pub mod zone;
+
+pub use date::DarsDate;
+pub use hijri::HijriDate;
+pub use model::*;
+pub use zone::Zone;
@@ -0,0 +1,167 @@
+// iustadji:// links teachers share with students:
+// iustadji://subscribe?url=https%3A%2F%2Fexample.org%2Fdars.json and iustadji://dars/16.03.2024/1
+use crate::date::DarsDate;
+
+pub const SCHEME: &str = "iustadji";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeepLink {
+    // add a feed to the sources, after the user confirmed it
+    Subscribe { url: String },
+    // open the card of one notification
+    Dars { date: DarsDate, id: String },
+}
+
+impl DeepLink {
+    pub fn parse(link: &str) -> Result<DeepLink, String> {
+        let rest = link
+            .split_once("://")
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case(SCHEME))
+            .map(|(_, rest)| rest)
+            .ok_or_else(|| format!("not an {} link: {}", SCHEME, link))?;
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+        match segments.as_slice() {
+            ["subscribe"] => {
+                let url = query
+                    .split('&')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find(|(key, _)| *key == "url")
+                    .map(|(_, value)| percent_decode(value))
+                    .transpose()?
+                    .ok_or_else(|| format!("missing url in {}", link))?;
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(format!("only http(s) feeds can be subscribed: {}", url));
+                }
+                Ok(DeepLink::Subscribe { url })
+            }
+            ["dars", date, id] if !id.is_empty() => Ok(DeepLink::Dars {
+                date: DarsDate::parse(date).ok_or_else(|| format!("invalid date {}", date))?,
+                id: percent_decode(id)?,
+            }),
+            _ => Err(format!("unknown link {}", link)),
+        }
+    }
+}
+
+// where the frontend router shows the card, feed ids may hold anything a path can't
+pub fn dars_path(date: DarsDate, id: &str) -> String {
+    format!("/dars/{}/{}", date, percent_encode(id))
+}
+
+// everything but the unreserved chars of RFC 3986, safe in a path segment and an element id
+pub fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            byte => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn percent_decode(value: &str) -> Result<String, String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut iter = value.bytes();
+    while let Some(byte) = iter.next() {
+        match byte {
+            b'%' => {
+                let hex = [iter.next(), iter.next()];
+                let decoded = match hex {
+                    [Some(hi), Some(lo)] => std::str::from_utf8(&[hi, lo])
+                        .ok()
+                        .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+                    _ => None,
+                };
+                bytes.push(decoded.ok_or_else(|| format!("invalid escape in {}", value))?);
+            }
+            b'+' => bytes.push(b' '),
+            byte => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| format!("invalid utf-8 in {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dars(date: &str, id: &str) -> DeepLink {
+        DeepLink::Dars {
+            date: DarsDate::parse(date).unwrap(),
+            id: id.to_string(),
+        }
+    }
+
+    #[test]
+    fn subscribe_links_decode_their_url() {
+        let link = DeepLink::parse(
+            "iustadji://subscribe?from=teacher&url=https%3A%2F%2Fexample.org%2Fdars.json%3Fclass%3D2",
+        );
+        assert_eq!(
+            link,
+            Ok(DeepLink::Subscribe {
+                url: "https://example.org/dars.json?class=2".to_string()
+            })
+        );
+        let link = DeepLink::parse("IUSTADJI://subscribe/?url=http://example.org/a+b.json");
+        assert_eq!(
+            link,
+            Ok(DeepLink::Subscribe {
+                url: "http://example.org/a b.json".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn subscribe_links_need_an_http_url() {
+        assert!(DeepLink::parse("iustadji://subscribe").is_err());
+        assert!(DeepLink::parse("iustadji://subscribe?feed=https://example.org").is_err());
+        assert!(DeepLink::parse("iustadji://subscribe?url=file%3A%2F%2F%2Fetc%2Fpasswd").is_err());
+        assert!(DeepLink::parse("iustadji://subscribe?url=https%3A%2").is_err());
+        assert!(DeepLink::parse("iustadji://subscribe?url=%FF").is_err());
+    }
+
+    #[test]
+    fn dars_links_point_at_a_card() {
+        assert_eq!(
+            DeepLink::parse("iustadji://dars/16.03.2024/1"),
+            Ok(dars("16.03.2024", "1"))
+        );
+        assert_eq!(
+            DeepLink::parse("iustadji://dars/2024-03-16/feed%2D1/"),
+            Ok(dars("16.03.2024", "feed-1"))
+        );
+        assert!(DeepLink::parse("iustadji://dars/16.03.2024").is_err());
+        assert!(DeepLink::parse("iustadji://dars/16.03.2024/").is_err());
+        assert!(DeepLink::parse("iustadji://dars/someday/1").is_err());
+    }
+
+    #[test]
+    fn other_links_are_refused() {
+        assert!(DeepLink::parse("https://example.org/dars.json").is_err());
+        assert!(DeepLink::parse("iustadji:dars/16.03.2024/1").is_err());
+        assert!(DeepLink::parse("iustadji://settings").is_err());
+    }
+
+    #[test]
+    fn card_paths_use_the_canonical_date() {
+        let date = DarsDate::parse("2024-03-16").unwrap();
+        assert_eq!(dars_path(date, "1"), "/dars/16.03.2024/1");
+    }
+
+    #[test]
+    fn ids_are_encoded_into_one_segment() {
+        let date = DarsDate::parse("16.03.2024").unwrap();
+        let id = "week 1/day?2#é%";
+        let path = dars_path(date, id);
+        assert_eq!(path, "/dars/16.03.2024/week%201%2Fday%3F2%23%C3%A9%25");
+        let link = format!("{}:/{}", SCHEME, path);
+        assert_eq!(DeepLink::parse(&link), Ok(dars("16.03.2024", id)));
+        assert_eq!(percent_encode("feed-1a_b.c~"), "feed-1a_b.c~");
+    }
+}
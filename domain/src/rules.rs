@@ -0,0 +1,46 @@
+use crate::hijri::{self, HijriDate};
+use chrono::{Datelike, NaiveDate, Weekday};
+
+// unknown rules never match; `validate` is where they get reported
+pub fn is_active(rules: &[String], today: NaiveDate) -> bool {
+    rules
+        .iter()
+        .any(|rule| rule_matches(rule, today).unwrap_or(false))
+}
+
+// a weekday ("friday") or a hijri month with optional day range ("dhul_hijjah:1-10")
+pub fn rule_matches(rule: &str, today: NaiveDate) -> Option<bool> {
+    let (name, days) = match rule.split_once(':') {
+        Some((name, days)) => (name.trim(), Some(days.trim())),
+        None => (rule.trim(), None),
+    };
+
+    if days.is_none() {
+        if let Ok(weekday) = name.parse::<Weekday>() {
+            return Some(today.weekday() == weekday);
+        }
+    }
+
+    let month = hijri::month_from_name(name)?;
+    let (first, last) = match days {
+        Some(days) => match days.split_once('-') {
+            Some((first, last)) => (first.trim().parse().ok()?, last.trim().parse().ok()?),
+            None => {
+                let day = days.parse().ok()?;
+                (day, day)
+            }
+        },
+        None => (1, 30),
+    };
+    let hijri = HijriDate::from_gregorian(today)?;
+    Some(hijri.month == month && (first..=last).contains(&hijri.day))
+}
+
+pub fn validate(rule: &str) -> Result<(), String> {
+    // any date will do, only the shape of the rule is checked
+    let today = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    match rule_matches(rule, today) {
+        Some(_) => Ok(()),
+        None => Err(format!("unknown activation rule: {}", rule)),
+    }
+}
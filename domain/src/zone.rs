@@ -0,0 +1,135 @@
+// The timezone dars dates, fixed times and the mute picker are read in. Scheduled times are
+// resolved to absolute ones once, so a DST change or travelling doesn't move them twice.
+use chrono::{DateTime, Local, LocalResult, NaiveDateTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    // whatever the system is set to at the moment, follows changes while running
+    System,
+    Named(Tz),
+}
+
+impl Zone {
+    // `None` is the system zone, otherwise an IANA name like "Europe/Istanbul"
+    pub fn parse(name: Option<&str>) -> Result<Zone, String> {
+        match name {
+            None => Ok(Zone::System),
+            Some(name) => name
+                .parse::<Tz>()
+                .map(Zone::Named)
+                .map_err(|_| format!("Unknown timezone: {}", name)),
+        }
+    }
+
+    pub fn local(self, at: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Zone::System => at.with_timezone(&Local).naive_local(),
+            Zone::Named(tz) => at.with_timezone(&tz).naive_local(),
+        }
+    }
+
+    // every name `parse` accepts, for the settings picker
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        chrono_tz::TZ_VARIANTS.iter().map(|tz| tz.name())
+    }
+
+    pub fn now(self) -> NaiveDateTime {
+        self.local(Utc::now())
+    }
+
+    // a time skipped by a DST change is moved forward by the length of the gap, a time
+    // that occurs twice is taken the first time
+    pub fn resolve(self, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            Zone::System => resolve_in(&Local, local),
+            Zone::Named(tz) => resolve_in(&tz, local),
+        }
+    }
+}
+
+fn resolve_in<Z: TimeZone>(zone: &Z, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+    match zone.from_local_datetime(&local) {
+        LocalResult::Single(at) => Some(at.with_timezone(&Utc)),
+        LocalResult::Ambiguous(first, _) => Some(first.with_timezone(&Utc)),
+        LocalResult::None => {
+            // read with the offset in effect before the gap, no gap is longer than a day
+            let before = zone
+                .from_local_datetime(&(local - TimeDelta::days(1)))
+                .earliest()?;
+            let offset = before.naive_local() - before.naive_utc();
+            Some((local - offset).and_utc())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn berlin() -> Zone {
+        Zone::parse(Some("Europe/Berlin")).unwrap()
+    }
+
+    fn local(at: &str) -> NaiveDateTime {
+        at.parse().unwrap()
+    }
+
+    fn utc(at: &str) -> DateTime<Utc> {
+        at.parse().unwrap()
+    }
+
+    #[test]
+    fn names_parse() {
+        assert_eq!(Zone::parse(None), Ok(Zone::System));
+        assert_eq!(
+            Zone::parse(Some("Asia/Dhaka")),
+            Ok(Zone::Named(chrono_tz::Asia::Dhaka))
+        );
+        assert!(Zone::parse(Some("Mars/Olympus")).is_err());
+        assert!(Zone::names().any(|name| name == "Europe/Istanbul"));
+    }
+
+    #[test]
+    fn plain_times_resolve_once() {
+        let zone = berlin();
+        assert_eq!(
+            zone.resolve(local("2024-01-15T12:00:00")),
+            Some(utc("2024-01-15T11:00:00Z"))
+        );
+        assert_eq!(
+            zone.resolve(local("2024-07-15T12:00:00")),
+            Some(utc("2024-07-15T10:00:00Z"))
+        );
+        assert_eq!(
+            zone.local(utc("2024-07-15T23:30:00Z")),
+            local("2024-07-16T01:30:00")
+        );
+    }
+
+    #[test]
+    fn times_skipped_by_dst_move_forward() {
+        // 02:00 to 03:00 doesn't exist on 31.03.2024, 02:30 is shown at 03:30
+        assert_eq!(
+            berlin().resolve(local("2024-03-31T02:30:00")),
+            Some(utc("2024-03-31T01:30:00Z"))
+        );
+        assert_eq!(
+            berlin().resolve(local("2024-03-31T03:00:00")),
+            Some(utc("2024-03-31T01:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn repeated_times_are_taken_the_first_time() {
+        // 02:00 to 03:00 happens twice on 27.10.2024, first in summer time
+        assert_eq!(
+            berlin().resolve(local("2024-10-27T02:30:00")),
+            Some(utc("2024-10-27T00:30:00Z"))
+        );
+        assert_eq!(
+            berlin().resolve(local("2024-10-27T03:30:00")),
+            Some(utc("2024-10-27T02:30:00Z"))
+        );
+    }
+}
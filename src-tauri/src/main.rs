@@ -1,178 +1,224 @@
-use chrono::{Local, NaiveDate};
-use rand::seq::SliceRandom;
-use reqwest;
-use serde::{Deserialize, Serialize};
-use std::fs;
+mod state;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use iustadji_core::action::Action;
+use iustadji_core::clock::ClockWatch;
+use iustadji_core::control::{self, Call, Reply};
+use iustadji_core::{fetch, secrets, Paths};
+use iustadji_domain::ipc::{
+    self, Args, CancelFetch, CancelSnooze, Command, Event, FetchStatus, FetchStatusChanged,
+    GetDars, GetFetchStatus, GetSettings, GetSnoozed, GetSourceSecrets, IpcResult, Navigate,
+    SetMute, SetSettings, SetSourceSecrets, SettingsChanged, Snooze, SnoozedChanged, TakeRoute,
+};
+use iustadji_domain::link::{self, DeepLink};
+use iustadji_domain::{split_id, Dars, DarsDate, NotificationData, Settings, Source, Zone};
+use notify_rust::Notification;
+use state::AppState;
 use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::Ordering;
+use std::{env, io, process};
 use std::{thread, time::Duration};
-use tauri::api::notification::Notification;
+use tauri::api::dialog;
 use tauri::{
-    CustomMenuItem, Manager, SystemTray, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem,
+    AppHandle, CustomMenuItem, Manager, RunEvent, State, SystemTray, SystemTrayEvent,
+    SystemTrayMenu, SystemTrayMenuItem,
 };
 
-#[macro_use]
-extern crate lazy_static;
-
-const DATA_URL: &str = "https://raw.githubusercontent.com/nazmul-pro/iustadji/data/dars.json";
-const DARS_FILE_PATH: &str = "/Applications/iUstadji.app/Contents/Resources/data/dars.json";
-const SETTINGS_FILE_PATH: &str = "/Applications/iUstadji.app/Contents/Resources/data/settings.json";
-
-lazy_static! {
-    static ref THREAD_IDS: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
-    static ref NOTIFICATIONS: Arc<Mutex<Vec<NotificationData>>> = Arc::new(Mutex::new(vec![]));
-    static ref MUTE_FOR: Arc<Mutex<u64>> = Arc::new(Mutex::new(0));
-    static ref APP_CONFIG: Arc<Mutex<String>> = Arc::new(Mutex::new(String::new()));
-    static ref SETTINGS_UPDATED: Arc<Mutex<bool>> = Arc::new(Mutex::new(false));
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct MuteDef {
-    recur: String,
-    start: String,
-    end: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct Settings {
-    data_url: String,
-    interval: u64,
-    dars_start_date: String,
-    dars_end_date: String,
-    mute_for: i32,
-    mute_def: Vec<MuteDef>,
-    pick_random: bool,
-    skip_ids: Vec<String>,
-}
-
-impl Default for Settings {
-    fn default() -> Self {
-        Settings {
-            data_url: DATA_URL.into(),
-            interval: 10,
-            dars_start_date: String::from("01.01.2024"),
-            dars_end_date: String::from("31.12.2025"),
-            mute_for: 0,
-            mute_def: Vec::new(),
-            pick_random: false,
-            skip_ids: Vec::new(),
-        }
-    }
+// tray id, title and when the mute ends, given now and the timezone of the settings
+type MutePreset = (
+    &'static str,
+    &'static str,
+    fn(DateTime<Utc>, Zone) -> DateTime<Utc>,
+);
+
+const MUTE_PRESETS: [MutePreset; 4] = [
+    ("mute_30", "Mute for 30 mins", |now, _| {
+        now + TimeDelta::minutes(30)
+    }),
+    ("mute_60", "Mute for 1 hr", |now, _| {
+        now + TimeDelta::hours(1)
+    }),
+    (
+        "mute_morning",
+        "Mute until tomorrow morning",
+        tomorrow_morning,
+    ),
+    ("mute_day", "Mute for a day", |now, _| {
+        now + TimeDelta::days(1)
+    }),
+];
+
+#[tauri::command]
+async fn get_dars(app: AppHandle) -> IpcResult<GetDars> {
+    load_sources(&app)
+        .await
+        .ok_or_else(|| "Loading the feeds was cancelled".to_string())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct NotificationData {
-    id: String,
-    title: String,
-    description: String,
+#[tauri::command]
+fn cancel_fetch(state: State<AppState>) -> IpcResult<CancelFetch> {
+    state.cancel_fetch();
+    Ok(())
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Dars {
-    date: String,
-    notifications: Vec<NotificationData>,
+#[tauri::command]
+fn get_fetch_status(state: State<AppState>) -> IpcResult<GetFetchStatus> {
+    Ok(state.fetch_status())
 }
 
-impl Default for Dars {
-    fn default() -> Self {
-        Self {
-            date: "01.01.2024".to_string(),
-            notifications: vec![NotificationData {
-                id: "start_id".to_string(),
-                title: "تسمية".to_string(),
-                description: "بِسْمِ ٱللَّٰهِ ٱلرَّحْمَٰنِ ٱلرَّحِيمِ".to_string(),
-            }],
+#[tauri::command]
+fn get_settings(state: State<AppState>) -> IpcResult<GetSettings> {
+    Ok(state.settings())
+}
+
+// off the main thread, removing secrets of dropped sources talks to the keyring
+#[tauri::command]
+async fn set_settings(app: AppHandle, args: Args<SetSettings>) -> IpcResult<SetSettings> {
+    let state = app.state::<AppState>();
+    let mut settings = args.settings;
+    let old = state.settings();
+    // the mute is changed through set_mute, the page may hold an older one
+    settings.muted_until = old.muted_until;
+    settings.mute_preset = old.mute_preset.clone();
+    state.save_settings(&settings)?;
+    // a removed source leaves nothing behind in the keyring
+    let kept = settings.all_sources();
+    for source in old.all_sources() {
+        if !kept.iter().any(|s| s.url == source.url) {
+            if let Err(err) = secrets::delete(&source.url) {
+                eprintln!("{}", err);
+            }
         }
     }
+    restart_notifications(&app);
+    refresh_tray(&app);
+    Ok(())
 }
 
 #[tauri::command]
-fn get_dars() -> String {
-    let dars = fetch_dars_data();
-    serde_json::to_string_pretty(&dars).expect("Ustadji: error parsing to JSON")
+fn set_mute(app: AppHandle, args: Args<SetMute>) -> IpcResult<SetMute> {
+    apply_mute(&app, None, args.until)
 }
 
+// off the main thread, the keyring may ask the user to unlock it
 #[tauri::command]
-fn get_settings_str() -> String {
-    serde_json::to_string_pretty(&get_settings()).expect("Ustadji: error parsing to JSON")
+async fn get_source_secrets(args: Args<GetSourceSecrets>) -> IpcResult<GetSourceSecrets> {
+    secrets::load(&args.url)
 }
 
 #[tauri::command]
-fn set_settings_str(data: String) -> String {
-    if let Ok(setting) = serde_json::from_str::<Settings>(&data) {
-        if let Ok(settings_json) = serde_json::to_string_pretty(&vec![setting]) {
-            if let Err(err) = fs::write(SETTINGS_FILE_PATH, settings_json) {
-                return format!("Failed to write settings file: {}", err);
-            } else {
-                *SETTINGS_UPDATED.lock().unwrap() = true;
-                thread::spawn(move || {
-                    populate_notifications();
-                    *SETTINGS_UPDATED.lock().unwrap() = false;
-                    init_notification(APP_CONFIG.lock().unwrap().clone());
-                });
+async fn set_source_secrets(args: Args<SetSourceSecrets>) -> IpcResult<SetSourceSecrets> {
+    secrets::save(&args.url, &args.secrets)
+}
 
-                return String::from("Settings successfully updated");
-            }
-        } else {
-            return String::from("Failed to serialize default settings to JSON");
-        }
-    } else {
-        return String::from("Error parsing settings data");
-    }
+#[tauri::command]
+fn get_snoozed(state: State<AppState>) -> IpcResult<GetSnoozed> {
+    Ok(state.snoozed())
 }
 
 #[tauri::command]
-fn get_settings() -> Settings {
-    let settings = {
-        let file_path = SETTINGS_FILE_PATH;
-
-        if !Path::new(file_path).exists() {
-            // If the file doesn't exist, create it with default settings
-            let default_settings = vec![Settings::default()];
-
-            // Serialize the default settings to JSON
-            let default_settings_json = serde_json::to_string_pretty(&default_settings)
-                .expect("Failed to serialize default settings to JSON");
-            // Create the settings file
-            fs::write(file_path, default_settings_json).expect("Failed to create settings file");
-        }
+fn snooze(app: AppHandle, args: Args<Snooze>) -> IpcResult<Snooze> {
+    snooze_for(&app, args.notification, args.minutes);
+    Ok(())
+}
+
+#[tauri::command]
+fn cancel_snooze(
+    app: AppHandle,
+    state: State<AppState>,
+    args: Args<CancelSnooze>,
+) -> IpcResult<CancelSnooze> {
+    if !state.cancel_snooze(&args.id) {
+        return Err(format!("{} is not snoozed", args.id));
+    }
+    emit::<SnoozedChanged>(&app, state.snoozed());
+    Ok(())
+}
 
-        let file_content = fs::read_to_string(file_path).expect("Ustadji: error reading file");
+#[tauri::command]
+fn take_route(state: State<AppState>) -> IpcResult<TakeRoute> {
+    Ok(state.pending_route.lock().unwrap().take())
+}
 
-        serde_json::from_str::<Vec<Settings>>(&file_content)
-            .expect("Ustadji: error serializing to JSON")
-            .first()
-            .unwrap()
-            .clone()
+// handler names have to match the contracts in iustadji_domain::ipc, their arguments are typed
+// by `Args` and their results by `IpcResult`
+macro_rules! assert_commands {
+    ($($command:ty => $handler:ident),* $(,)?) => {
+        $(const _: () = {
+            let _ = $handler;
+            assert!(ipc::same_name(<$command as Command>::NAME, stringify!($handler)));
+        };)*
     };
-    settings
+}
+
+assert_commands! {
+    GetDars => get_dars,
+    CancelFetch => cancel_fetch,
+    GetFetchStatus => get_fetch_status,
+    GetSettings => get_settings,
+    SetSettings => set_settings,
+    SetMute => set_mute,
+    GetSourceSecrets => get_source_secrets,
+    SetSourceSecrets => set_source_secrets,
+    GetSnoozed => get_snoozed,
+    Snooze => snooze,
+    CancelSnooze => cancel_snooze,
+    TakeRoute => take_route,
 }
 
 fn main() {
-    fetch_dars_data();
+    let args: Vec<String> = env::args().skip(1).collect();
+    #[cfg(target_os = "macos")]
+    tauri_plugin_deep_link::prepare("com.iustadji-mac.dev");
+    let state = AppState::new(Paths::default());
+    let socket = state.paths.socket.clone();
+    // a second launch only hands its arguments to the running instance; a running daemon stops
+    // instead and frees the socket, so the reminders are only ever shown by one of them
+    if control::is_running(&socket) {
+        forward_launch(&socket, args.clone());
+    }
+
     let quit = CustomMenuItem::new("quit".to_string(), "Quit");
     let open: CustomMenuItem = CustomMenuItem::new("open".to_string(), "Open");
+    // titles of the disabled ones are filled in by refresh_tray
+    let status: CustomMenuItem = CustomMenuItem::new("status".to_string(), "Not muted").disabled();
+    let last: CustomMenuItem =
+        CustomMenuItem::new("last".to_string(), "Nothing shown yet").disabled();
+    let next: CustomMenuItem = CustomMenuItem::new("next".to_string(), "Show next now");
+    let sources: CustomMenuItem = CustomMenuItem::new("sources".to_string(), "").disabled();
     let unmute: CustomMenuItem = CustomMenuItem::new("unmute".to_string(), "Unmute");
-    let mute_30: CustomMenuItem = CustomMenuItem::new("mute_30".to_string(), "Mute for 30 mins");
-    let mute_60: CustomMenuItem = CustomMenuItem::new("mute_60".to_string(), "Mute for 1 hr");
-    let mute_restart: CustomMenuItem =
-        CustomMenuItem::new("mute_restart".to_string(), "Mute until unmute/restart");
 
-    let tray_menu = SystemTrayMenu::new()
+    let mut tray_menu = SystemTrayMenu::new()
+        .add_item(status)
+        .add_item(last)
+        .add_item(next)
+        .add_item(sources)
+        .add_native_item(SystemTrayMenuItem::Separator)
         .add_item(open)
         .add_native_item(SystemTrayMenuItem::Separator)
-        .add_item(unmute)
-        .add_item(mute_30)
-        .add_item(mute_60)
-        .add_item(mute_restart)
+        .add_item(unmute);
+    for (id, title, _) in MUTE_PRESETS {
+        tray_menu = tray_menu.add_item(CustomMenuItem::new(id.to_string(), title));
+    }
+    let tray_menu = tray_menu
         .add_native_item(SystemTrayMenuItem::Separator)
         .add_item(quit);
 
     let app = tauri::Builder::default()
+        .manage(state)
         .invoke_handler(tauri::generate_handler![
             get_dars,
-            get_settings_str,
-            set_settings_str
+            cancel_fetch,
+            get_fetch_status,
+            get_settings,
+            set_settings,
+            set_mute,
+            get_source_secrets,
+            set_source_secrets,
+            get_snoozed,
+            snooze,
+            cancel_snooze,
+            take_route
         ])
         .on_window_event(|event| match event.event() {
             tauri::WindowEvent::CloseRequested { api, .. } => {
@@ -194,159 +240,499 @@ fn main() {
                     std::process::exit(0);
                 }
                 "open" => {
-                    let window = app.get_window("main").unwrap();
-                    window.set_focus().unwrap();
-                    window.show().unwrap();
+                    show_main_window(app);
                 }
-                "unmute" => {
-                    *MUTE_FOR.lock().unwrap() = 0; // change to real value self, below and sleep time
+                "last" => {
+                    let last = app.state::<AppState>().last_shown.lock().unwrap().clone();
+                    if let Some(last) = last {
+                        notify(app, &last);
+                    }
                 }
-                "mute_30" => {
-                    *MUTE_FOR.lock().unwrap() = 30;
+                "next" => {
+                    app.state::<AppState>()
+                        .show_next
+                        .store(true, Ordering::SeqCst);
                 }
-                "mute_60" => {
-                    *MUTE_FOR.lock().unwrap() = 60;
+                "unmute" => {
+                    if let Err(err) = apply_mute(app, None, None) {
+                        eprintln!("{}", err);
+                    }
                 }
-                "mute_restart" => {
-                    *MUTE_FOR.lock().unwrap() = 1440; // 1d
+                id => {
+                    if let Some(&(id, _, until)) = MUTE_PRESETS.iter().find(|p| p.0 == id) {
+                        let zone = app.state::<AppState>().settings().zone();
+                        let until = until(Utc::now(), zone);
+                        if let Err(err) = apply_mute(app, Some(id), Some(until)) {
+                            eprintln!("{}", err);
+                        }
+                    }
                 }
-                _ => {}
             },
             _ => {}
         })
         .build(tauri::generate_context!("tauri.conf.json"))
         .expect("error while building tauri application");
-    *APP_CONFIG.lock().unwrap() = app.config().tauri.bundle.identifier.clone();
+    // notifications are attributed to the bundle, not to a terminal
+    #[cfg(target_os = "macos")]
+    if let Err(err) = notify_rust::set_application(&app.config().tauri.bundle.identifier) {
+        eprintln!("{}", err);
+    }
 
-    populate_notifications();
+    // elsewhere links come as launch arguments, on macOS as Apple Events to the running app
+    #[cfg(target_os = "macos")]
+    {
+        let handle = app.handle();
+        if let Err(err) =
+            tauri_plugin_deep_link::register(link::SCHEME, move |link| open_link(&handle, &link))
+        {
+            eprintln!("Failed to handle {} links: {}", link::SCHEME, err);
+        }
+    }
+
+    let handle = app.handle();
+    match control::serve(&socket, move |call| handle_control(&handle, call)) {
+        Ok(()) => {}
+        // started at the same time as another one and lost, a second scheduler would show every
+        // reminder twice
+        Err(err) if err.kind() == io::ErrorKind::AddrInUse => {
+            forward_launch(&socket, args.clone());
+            eprintln!("Another instance keeps the control socket, quitting");
+            process::exit(1);
+        }
+        Err(err) => eprintln!("Control socket unavailable: {}", err),
+    }
 
-    init_notification(APP_CONFIG.lock().unwrap().clone());
+    refresh_tray(&app.handle());
+    // the first fetch runs on its own thread, the window opens meanwhile
+    restart_notifications(&app.handle());
+    init_scheduled_notification(app.handle());
 
     // Run the app
-    app.run(|_app_handle, _event| {});
-}
-
-fn populate_notifications() {
-    let all_dars = fetch_dars_data();
-    let settings = get_settings();
-    let mut rng = rand::thread_rng();
-    let mut all_notif: Vec<NotificationData> = vec![];
-
-    for dars in all_dars {
-        if let Ok(start_date) = NaiveDate::parse_from_str(&settings.dars_start_date, "%d.%m.%Y") {
-            if let Ok(end_date) = NaiveDate::parse_from_str(&settings.dars_end_date, "%d.%m.%Y") {
-                let notifications = dars.notifications.clone();
-
-                for notification in notifications {
-                    if let Ok(notification_date) = NaiveDate::parse_from_str(&dars.date, "%d.%m.%Y")
-                    {
-                        if notification_date >= start_date && notification_date <= end_date {
-                            let notif = NotificationData {
-                                id: String::from(&dars.date) + &notification.id,
-                                title: notification.title,
-                                description: notification.description,
-                            };
-                            all_notif.push(notif);
-                        }
-                    }
-                }
-            }
+    app.run(move |app_handle, event| {
+        // dialogs for links need the event loop, so the launch args wait for it
+        if let RunEvent::Ready = event {
+            handle_launch_args(app_handle, &args);
         }
+    });
+}
+
+// `option` is the tray preset it came from
+fn apply_mute(
+    app: &AppHandle,
+    option: Option<&'static str>,
+    until: Option<DateTime<Utc>>,
+) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let mut settings = state.settings();
+    settings.muted_until = until;
+    settings.mute_preset = option.map(str::to_string);
+    state.save_settings(&settings)?;
+    emit::<SettingsChanged>(app, settings);
+    refresh_tray(app);
+    Ok(())
+}
+
+// 07:00 of the next day
+fn tomorrow_morning(now: DateTime<Utc>, zone: Zone) -> DateTime<Utc> {
+    zone.local(now)
+        .date()
+        .succ_opt()
+        .and_then(|date| date.and_hms_opt(7, 0, 0))
+        .and_then(|morning| zone.resolve(morning))
+        .unwrap_or(now + TimeDelta::days(1))
+}
+
+// exits once the running instance took over, returns when it declined, like the daemon does
+fn forward_launch(socket: &Path, args: Vec<String>) {
+    match control::call(socket, Call::Open { args }) {
+        Ok(_) => process::exit(0),
+        Err(err) => eprintln!("Starting: {}", err),
     }
-    if settings.pick_random {
-        all_notif.shuffle(&mut rng);
+}
+
+fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_window("main") {
+        window.show().unwrap();
+        window.set_focus().unwrap();
     }
-    *NOTIFICATIONS.lock().unwrap() = all_notif;
-}
-
-fn fetch_dars_data() -> Vec<Dars> {
-    let settings = get_settings();
-    let mut tried = 0;
-    loop {
-        tried += 1;
-        match reqwest::blocking::get(&settings.data_url) {
-            Ok(response) => match response.text() {
-                Ok(body) => match serde_json::from_str::<Vec<Dars>>(&body) {
-                    Ok(all_dars) => {
-                        return all_dars;
-                    }
-                    Err(err) => {
-                        eprintln!("Failed to parse JSON: {}", err);
+}
+
+// flags like --mute 30, either given to this launch or forwarded by a later one
+fn handle_launch_args(app: &AppHandle, args: &[String]) {
+    let (calls, rest) = control::calls_from_args(args);
+    for call in calls {
+        if let Err(err) = handle_control(app, call) {
+            eprintln!("{}", err);
+        }
+    }
+    let prefix = format!("{}://", link::SCHEME);
+    for arg in rest {
+        if arg.to_ascii_lowercase().starts_with(&prefix) {
+            open_link(app, &arg);
+        } else {
+            eprintln!("Ignoring argument {}", arg);
+        }
+    }
+}
+
+fn emit<E: Event>(app: &AppHandle, payload: E::Payload) {
+    if let Err(err) = app.emit_all(E::NAME, payload) {
+        eprintln!("Failed to emit {}: {}", E::NAME, err);
+    }
+}
+
+fn open_link(app: &AppHandle, link: &str) {
+    match DeepLink::parse(link) {
+        Ok(DeepLink::Dars { date, id }) => open_dars(app, date, &id),
+        Ok(DeepLink::Subscribe { url }) => {
+            show_main_window(app);
+            let window = app.get_window("main");
+            let app = app.clone();
+            dialog::ask(
+                window.as_ref(),
+                "Subscribe",
+                format!("Add {} to your sources?", url),
+                move |yes| {
+                    if yes {
+                        subscribe(&app, url);
                     }
                 },
-                Err(err) => {
-                    eprintln!("Failed to read response body: {}", err);
-                }
-            },
-            Err(err) => {
-                eprintln!("Failed to fetch JSON: {}", err);
-            }
+            );
+        }
+        Err(err) => eprintln!("{}", err),
+    }
+}
+
+// the webview may not listen yet, so it also asks for the route once it mounts
+fn open_dars(app: &AppHandle, date: DarsDate, id: &str) {
+    *app.state::<AppState>().pending_route.lock().unwrap() = Some(link::dars_path(date, id));
+    show_main_window(app);
+    emit::<Navigate>(app, ());
+}
+
+fn subscribe(app: &AppHandle, url: String) {
+    let window = app.get_window("main");
+    let state = app.state::<AppState>();
+    let mut settings = state.settings();
+    if settings.all_sources().iter().any(|s| s.url == url) {
+        dialog::message(
+            window.as_ref(),
+            "Subscribe",
+            format!("{} is already a source", url),
+        );
+        return;
+    }
+    settings.sources.push(Source::new(url));
+    if let Err(err) = state.save_settings(&settings) {
+        dialog::message(window.as_ref(), "Subscribe", err);
+        return;
+    }
+    emit::<SettingsChanged>(app, settings);
+    restart_notifications(app);
+    refresh_tray(app);
+}
+
+// requests from the cli, launchers and keybindings, see iustadji_core::control
+fn handle_control(app: &AppHandle, call: Call) -> Reply {
+    match call {
+        Call::Open { args } => {
+            show_main_window(app);
+            handle_launch_args(app, &args);
+            return Ok(None);
+        }
+        Call::Mute { minutes } => {
+            let until = Utc::now() + TimeDelta::minutes(minutes as i64);
+            apply_mute(app, None, Some(until))?;
+        }
+        Call::Unmute => apply_mute(app, None, None)?,
+        Call::Status => {}
+        Call::Next => {
+            app.state::<AppState>()
+                .show_next
+                .store(true, Ordering::SeqCst);
+            return Ok(None);
         }
-        if tried > 3 {
-            if let Ok(file_content) = fs::read_to_string(DARS_FILE_PATH) {
-                // will serve data from local json file if not resolve api after 30 sec
-                return serde_json::from_str::<Vec<Dars>>(&file_content).unwrap();
+        Call::Reload => {
+            restart_notifications(app);
+            return Ok(None);
+        }
+    }
+    Ok(Some(app.state::<AppState>().status()))
+}
+
+// settings changed, rebuild the queue and start the rotation over
+fn restart_notifications(app: &AppHandle) {
+    // the running rotation stops waiting right away
+    let generation = app.state::<AppState>().restart();
+    let app = app.clone();
+    thread::spawn(move || {
+        rebuild(&app);
+        // another restart while fetching starts the rotation itself
+        if app.state::<AppState>().is_current(generation) {
+            init_notification(app);
+        }
+    });
+}
+
+// on tauri's runtime, each source reports to the window as it goes and is kept for one that
+// opens later; `None` once cancelled
+async fn load_sources(app: &AppHandle) -> Option<Vec<Dars>> {
+    let state = app.state::<AppState>();
+    let settings = state.settings();
+    let cancel = state.fetch_token();
+    let progress = |status: FetchStatus| {
+        state.set_fetch_status(status.clone());
+        emit::<FetchStatusChanged>(app, status);
+    };
+    let key = (settings.all_sources(), settings.timezone.clone());
+    let fetch = fetch::load_sources(&settings, &state.paths, &progress, &cancel);
+    state.load(key, fetch).await
+}
+
+// for the reminder threads, a cancelled fetch keeps the queue it had
+fn rebuild(app: &AppHandle) {
+    if let Some(all_dars) = tauri::async_runtime::block_on(load_sources(app)) {
+        app.state::<AppState>().rebuild(&all_dars);
+    }
+}
+
+// back after `minutes` while the rotation goes on
+fn snooze_for(app: &AppHandle, notification: NotificationData, minutes: u64) {
+    let state = app.state::<AppState>();
+    state.snooze(
+        notification,
+        Utc::now() + TimeDelta::minutes(minutes as i64),
+    );
+    emit::<SnoozedChanged>(app, state.snoozed());
+}
+
+// clicking the notification opens its card, the handle waits for that on its own thread
+fn notify(app: &AppHandle, notification: &NotificationData) {
+    let state = app.state::<AppState>();
+    let settings = state.settings();
+    let mut toast = Notification::new();
+    toast
+        .appname("iUstadji")
+        .summary(&notification.title)
+        .body(&notification.description)
+        .action(Action::Open.id(), &Action::Open.label(&settings));
+    if supports_actions() {
+        for action in Action::BUTTONS {
+            toast.action(action.id(), &action.label(&settings));
+        }
+    }
+    let handle = match toast.show() {
+        Ok(handle) => handle,
+        Err(err) => {
+            eprintln!("Failed to show notification: {}", err);
+            return;
+        }
+    };
+    *state.last_shown.lock().unwrap() = Some(notification.clone());
+    refresh_tray(app);
+
+    let app = app.clone();
+    let notification = notification.clone();
+    thread::spawn(move || {
+        handle.wait_for_action(|action| {
+            if let Some(action) = Action::from_id(action) {
+                on_action(&app, action, notification);
             }
-        } else {
-            // Retry after 10 seconds
-            std::thread::sleep(Duration::from_secs(10));
+        })
+    });
+}
+
+// freedesktop servers list the buttons they can show, elsewhere a reminder stays a plain one
+#[cfg(all(unix, not(target_os = "macos")))]
+fn supports_actions() -> bool {
+    notify_rust::get_capabilities().is_ok_and(|caps| caps.iter().any(|cap| cap == "actions"))
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn supports_actions() -> bool {
+    false
+}
+
+fn on_action(app: &AppHandle, action: Action, notification: NotificationData) {
+    let state = app.state::<AppState>();
+    match action {
+        Action::Open => match split_id(&notification.id) {
+            Some((date, id)) => open_dars(app, date, id),
+            None => show_main_window(app),
+        },
+        Action::Snooze => snooze_for(app, notification, state.settings().snooze_minutes),
+        // taken out of the queue on its next rebuild
+        Action::Skip | Action::Learned => {
+            let mut settings = state.settings();
+            if action == Action::Skip {
+                settings.skip(&notification.id);
+            } else {
+                settings.mark_learned(&notification.id);
+            }
+            if let Err(err) = state.save_settings(&settings) {
+                eprintln!("{}", err);
+                return;
+            }
+            emit::<SettingsChanged>(app, settings);
         }
     }
 }
 
-fn init_notification(app_config: String) {
+// mute state, last reminder and source count; also called every 30s so the remaining time
+// counts down and a mute from the cli shows up
+fn refresh_tray(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let tray = app.tray_handle();
+    let now = Utc::now();
+    let until = state.muted_until();
+    let muted = until.is_some();
+    // stored with the mute, so the checkmark survives a restart
+    let option = state.settings().mute_preset.filter(|_| muted);
+    let status = match until {
+        Some(until) => format!("Muted, {} left", remaining(until - now)),
+        None => "Not muted".to_string(),
+    };
+    let last = state.last_shown.lock().unwrap().clone();
+    let sources = match state.settings().all_sources().len() {
+        1 => "1 source".to_string(),
+        count => format!("{} sources", count),
+    };
+
+    let mut results = vec![
+        tray.get_item("status").set_title(status),
+        tray.get_item("sources").set_title(sources),
+        tray.get_item("unmute").set_enabled(muted),
+    ];
+    if let Some(last) = last {
+        let item = tray.get_item("last");
+        results.push(item.set_title(format!("Last: {}", shorten(&last.title, 40))));
+        results.push(item.set_enabled(true));
+    }
+    for (id, _, _) in MUTE_PRESETS {
+        results.push(
+            tray.get_item(id)
+                .set_selected(option.as_deref() == Some(id)),
+        );
+    }
+    for err in results.into_iter().filter_map(Result::err) {
+        eprintln!("Failed to update tray: {}", err);
+    }
+}
+
+fn remaining(left: TimeDelta) -> String {
+    // rounded up, a mute with seconds left still reads 1 min
+    let minutes = (left.num_seconds() + 59) / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{} min", minutes),
+        (hours, minutes) => format!("{} h {:02} min", hours, minutes),
+    }
+}
+
+fn shorten(title: &str, max: usize) -> String {
+    match title.char_indices().nth(max) {
+        Some((end, _)) => format!("{}…", &title[..end]),
+        None => title.to_string(),
+    }
+}
+
+// against the wall clock, so a suspend doesn't stretch the interval; a suspend or clock change
+// starts the wait over rather than ending it with a reminder right on wake. true when interrupted
+fn wait_interval(settings: &Settings, interrupted: impl Fn() -> bool) -> bool {
+    let interval = TimeDelta::minutes(settings.interval as i64);
+    let zone = settings.zone();
+    let mut clock = ClockWatch::new(zone.now());
+    let mut deadline = Utc::now() + interval;
+    while Utc::now() < deadline {
+        if interrupted() {
+            return true;
+        }
+        thread::sleep(Duration::from_secs(1));
+        if clock.check(zone.now()).is_some() {
+            deadline = Utc::now() + interval;
+        }
+    }
+    false
+}
+
+fn init_notification(app: AppHandle) {
+    let generation = app.state::<AppState>().restart();
     thread::spawn(move || {
-        let t_id = Local::now().to_string();
-        THREAD_IDS.lock().unwrap().push(t_id.clone());
-        let settings = get_settings();
-        let mut count = NOTIFICATIONS.lock().unwrap().len();
-
-        for notification in NOTIFICATIONS.lock().unwrap().iter() {
-            // close this slept thread if init from anywhere
-            if *THREAD_IDS.lock().unwrap().last().unwrap() != t_id {
-                break;
+        let state = app.state::<AppState>();
+        let settings = state.settings();
+
+        if !state.has_rotation() {
+            // nothing in the window yet, look again after one interval
+            if wait_interval(&settings, || !state.is_current(generation)) {
+                return;
+            }
+            rebuild(&app);
+            // restarted while fetching, which can take minutes of retries
+            if state.is_current(generation) {
+                init_notification(app.clone());
             }
+            return;
+        }
+
+        // close this slept thread if init from anywhere
+        while state.is_current(generation) {
+            let Some(notification) = state.next_notification() else {
+                break;
+            };
 
-            if settings.interval > *MUTE_FOR.lock().unwrap() {
+            if !state.is_muted() {
                 println!(
-                    "msg {} mute for = {} interval = {}",
-                    &notification.description,
-                    *MUTE_FOR.lock().unwrap(),
-                    settings.interval
+                    "msg {} interval = {}",
+                    &notification.description, settings.interval
                 );
-                // Shows a notification with the given title and body
-                Notification::new(&app_config)
-                    .title(&notification.title)
-                    .body(&notification.description)
-                    .show()
-                    .unwrap();
-
-                // break per min for smooth transition btwn two save settings of diff interval
-                for i in 0..settings.interval {
-                    // wip for quick transition while save settings
-                    println!("loop {} interval {}", i, settings.interval);
-                    if *SETTINGS_UPDATED.lock().unwrap() {
-                        break;
-                    }
-                    thread::sleep(Duration::from_secs(60)); // 60
-                }
+                notify(&app, &notification);
+
+                // cut short for smooth transition btwn two save settings of diff interval
+                // and to show the next one right away when asked to
+                state.show_next.store(false, Ordering::SeqCst);
+                wait_interval(&settings, || {
+                    !state.is_current(generation) || state.show_next.load(Ordering::SeqCst)
+                });
             } else {
-                let mute_for = *MUTE_FOR.lock().unwrap();
-                // break per min for smooth transition btwn mute/unmute
-                for _ in 0..mute_for {
+                // check per min for smooth transition btwn mute/unmute
+                while state.is_muted() && state.is_current(generation) {
                     thread::sleep(Duration::from_secs(60)); // 60
-                    if *MUTE_FOR.lock().unwrap() == 0 {
-                        break;
-                    }
                 }
-                *MUTE_FOR.lock().unwrap() = 0;
             };
+        }
 
-            count = count - 1;
-            if count == 0 {
-                // notify from start
-                init_notification(app_config.clone());
+        // rebuild the queue so relative windows follow the current date, then notify from start
+        if state.is_current(generation) {
+            rebuild(&app);
+            if state.is_current(generation) {
+                init_notification(app.clone());
             }
         }
     });
 }
+
+fn init_scheduled_notification(app: AppHandle) {
+    // fed the time of the configured zone, so a change of the system timezone or DST counts
+    // as a jump too and the timed reminders are resolved again
+    let mut clock = ClockWatch::new(app.state::<AppState>().settings().zone().now());
+    thread::spawn(move || loop {
+        let state = app.state::<AppState>();
+        let now = Utc::now();
+        // after a suspend or a clock change the timed reminders are resolved again while the
+        // rotation goes on where it was, whatever came due meanwhile is dropped instead of shown
+        // in a burst
+        if let Some(jump) = clock.check(state.settings().zone().now()) {
+            println!("clock jumped by {} min, rescheduling", jump.num_minutes());
+            state.reschedule();
+        }
+        let (due, snoozed_due) = state.due_notifications(now);
+        for notification in due {
+            notify(&app, &notification);
+        }
+        if snoozed_due {
+            emit::<SnoozedChanged>(&app, state.snoozed());
+        }
+
+        refresh_tray(&app);
+        thread::sleep(Duration::from_secs(30));
+    });
+}
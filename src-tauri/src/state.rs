@@ -0,0 +1,216 @@
+use chrono::{DateTime, Utc};
+use iustadji_core::control::Status;
+use iustadji_core::{store, Paths, Queue};
+use iustadji_domain::ipc::{FetchStatus, Snoozed};
+use iustadji_domain::{Dars, NotificationData, Settings, Source};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+// what a load of the feeds depends on: the sources and the timezone their dates are read in
+pub type LoadKey = (Vec<Source>, Option<String>);
+
+// everything the commands, the tray and the reminder threads share, handed to tauri's `manage`;
+// locks are only held to copy in or out, never across a notification or a sleep
+pub struct AppState {
+    pub paths: Paths,
+    // the feeds last loaded, to hand to a caller that waited for that load and to resolve timed
+    // reminders again after a jump
+    loaded: Mutex<(LoadKey, Vec<Dars>)>,
+    loads: AtomicU64,
+    loading: tokio::sync::Mutex<()>,
+    // the settings last read or saved, used while the file can't be read
+    last_settings: Mutex<Settings>,
+    queue: Mutex<Queue>,
+    // bumped on every restart, a rotation thread of an older one stops at its next check
+    generation: AtomicU64,
+    pub show_next: AtomicBool,
+    pub pending_route: Mutex<Option<String>>,
+    pub last_shown: Mutex<Option<NotificationData>>,
+    // parent of the token every fetch runs with, replaced once cancelled
+    fetching: Mutex<CancellationToken>,
+    // the last one reported per source url
+    fetch_status: Mutex<Vec<FetchStatus>>,
+}
+
+impl AppState {
+    pub fn new(paths: Paths) -> AppState {
+        let settings = store::load_settings(&paths).unwrap_or_else(|err| {
+            eprintln!("{}, starting with the default settings", err);
+            Settings::default()
+        });
+        AppState {
+            paths,
+            last_settings: Mutex::new(settings),
+            loaded: Mutex::new(((vec![], None), vec![])),
+            loads: AtomicU64::new(0),
+            loading: tokio::sync::Mutex::new(()),
+            queue: Mutex::new(Queue::default()),
+            generation: AtomicU64::new(0),
+            show_next: AtomicBool::new(false),
+            pending_route: Mutex::new(None),
+            last_shown: Mutex::new(None),
+            fetching: Mutex::new(CancellationToken::new()),
+            fetch_status: Mutex::new(vec![]),
+        }
+    }
+
+    // read on every use, the cli changes the file behind our back; a file that can't be read,
+    // e.g. edited by hand into invalid JSON, leaves the last good settings in place
+    pub fn settings(&self) -> Settings {
+        match store::load_settings(&self.paths) {
+            Ok(settings) => {
+                *self.last_settings.lock().unwrap() = settings.clone();
+                settings
+            }
+            Err(err) => {
+                eprintln!("{}, keeping the last settings", err);
+                self.last_settings.lock().unwrap().clone()
+            }
+        }
+    }
+
+    pub fn save_settings(&self, settings: &Settings) -> Result<(), String> {
+        store::save_settings(&self.paths, settings)?;
+        *self.last_settings.lock().unwrap() = settings.clone();
+        Ok(())
+    }
+
+    // the cli can mute from outside, so the stored settings win over the last ones seen here
+    pub fn muted_until(&self) -> Option<DateTime<Utc>> {
+        self.settings().active_mute(Utc::now())
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted_until().is_some()
+    }
+
+    // ends the running rotation, the returned generation is the one of the next
+    pub fn restart(&self) -> u64 {
+        self.generation.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.generation.load(Ordering::SeqCst) == generation
+    }
+
+    pub fn fetch_token(&self) -> CancellationToken {
+        self.fetching.lock().unwrap().child_token()
+    }
+
+    // stops every fetch in progress, later ones run again
+    pub fn cancel_fetch(&self) {
+        let mut fetching = self.fetching.lock().unwrap();
+        fetching.cancel();
+        *fetching = CancellationToken::new();
+    }
+
+    pub fn set_fetch_status(&self, status: FetchStatus) {
+        let mut all = self.fetch_status.lock().unwrap();
+        match all.iter_mut().find(|s| s.url == status.url) {
+            Some(last) => *last = status,
+            None => all.push(status),
+        }
+    }
+
+    // in the order of the sources, ones no longer configured are left out
+    pub fn fetch_status(&self) -> Vec<FetchStatus> {
+        let sources = self.settings().all_sources();
+        let all = self.fetch_status.lock().unwrap();
+        sources
+            .iter()
+            .filter_map(|source| all.iter().find(|s| s.url == source.url).cloned())
+            .collect()
+    }
+
+    // one fetch at a time; a caller that waited for another one of the same sources takes its
+    // result instead of fetching again, e.g. the window opening during the first load
+    pub async fn load(
+        &self,
+        key: LoadKey,
+        fetch: impl Future<Output = Option<Vec<Dars>>>,
+    ) -> Option<Vec<Dars>> {
+        let seen = self.loads.load(Ordering::SeqCst);
+        let _loading = self.loading.lock().await;
+        {
+            let loaded = self.loaded.lock().unwrap();
+            if self.loads.load(Ordering::SeqCst) != seen && loaded.0 == key {
+                return Some(loaded.1.clone());
+            }
+        }
+        let all_dars = fetch.await?;
+        *self.loaded.lock().unwrap() = (key, all_dars.clone());
+        self.loads.fetch_add(1, Ordering::SeqCst);
+        Some(all_dars)
+    }
+
+    // snoozed reminders outlive the rebuild
+    pub fn rebuild(&self, all_dars: &[Dars]) {
+        let settings = self.settings();
+        self.queue
+            .lock()
+            .unwrap()
+            .rebuild(all_dars, &settings, Utc::now());
+    }
+
+    // after a clock jump: timed reminders only, the rotation keeps its place
+    pub fn reschedule(&self) {
+        let settings = self.settings();
+        let loaded = self.loaded.lock().unwrap();
+        self.queue
+            .lock()
+            .unwrap()
+            .reschedule(&loaded.1, &settings, Utc::now());
+    }
+
+    // `None` once the rotation is used up; taken one at a time, so a rebuild or a status
+    // request never waits for the rotation
+    pub fn next_notification(&self) -> Option<NotificationData> {
+        self.queue.lock().unwrap().next_notification()
+    }
+
+    pub fn has_rotation(&self) -> bool {
+        !self.queue.lock().unwrap().rotation().is_empty()
+    }
+
+    pub fn snooze(&self, notification: NotificationData, at: DateTime<Utc>) {
+        self.queue.lock().unwrap().snooze(notification, at);
+    }
+
+    pub fn cancel_snooze(&self, id: &str) -> bool {
+        self.queue.lock().unwrap().cancel_snooze(id)
+    }
+
+    pub fn snoozed(&self) -> Vec<Snoozed> {
+        self.queue
+            .lock()
+            .unwrap()
+            .snoozed()
+            .map(|s| Snoozed {
+                at: s.at,
+                notification: s.notification.clone(),
+            })
+            .collect()
+    }
+
+    // the timed reminders to show now, see `Queue::due_notifications`; true as well when any
+    // snoozed one came due, shown or put back until the mute ends
+    pub fn due_notifications(&self, now: DateTime<Utc>) -> (Vec<NotificationData>, bool) {
+        let muted_until = self.settings().active_mute(now);
+        let mut queue = self.queue.lock().unwrap();
+        let snoozed_due = queue.snoozed().any(|s| s.at <= now);
+        (queue.due_notifications(now, muted_until), snoozed_due)
+    }
+
+    pub fn status(&self) -> Status {
+        let muted_until = self.muted_until();
+        let queue = self.queue.lock().unwrap();
+        Status {
+            muted_until,
+            rotation: queue.rotation().len(),
+            scheduled: queue.scheduled().len(),
+            next_at: queue.scheduled().first().map(|s| s.at),
+        }
+    }
+}
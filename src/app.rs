@@ -1,83 +1,31 @@
+use std::collections::BTreeMap;
 use std::time::Duration;
-use chrono::{Local, NaiveDate};
-use leptos::logging::log;
+use crate::ipc;
+use chrono::{NaiveDate, NaiveTime, Utc};
+use iustadji_domain::{date, link};
+use iustadji_domain::ipc::{FetchState, FetchStatus, FetchStatusChanged, Navigate, SettingsChanged, Snoozed, SnoozedChanged};
+use iustadji_domain::{composite_id, split_id, AnchorDef, Dars, DarsDate, DarsWindow, HijriDate, NotificationData, Settings, Source, SourceAuth, SourceSecrets, Zone};
+use leptos::logging::{error, log};
 use leptos::*;
 use leptos_router::*;
-use serde::{Deserialize, Serialize};
-use serde_wasm_bindgen::to_value;
 use thaw::mobile::{show_toast, ToastOptions};
-use thaw::{Button, ButtonColor, Divider, Input, TimePicker};
-use thaw::{DatePicker, InputNumber, SignalWatch, Switch};
-use wasm_bindgen::prelude::*;
-
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "tauri"])]
-    async fn invoke(cmd: &str, args: JsValue) -> JsValue;
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct DarsArg {
-    date: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SettingsArg {
-    data: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct NotificationData {
-    id: String,
-    title: String,
-    description: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct Dars {
-    date: String,
-    notifications: Vec<NotificationData>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct MuteDef {
-    recur: String,
-    start: String,
-    end: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct Settings {
-    data_url: String,
-    interval: u64,
-    dars_start_date: String,
-    dars_end_date: String,
-    mute_for: i32,
-    mute_def: Vec<MuteDef>,
-    pick_random: bool,
-    skip_ids: Vec<String>,
-}
-
-impl Default for Settings {
-    fn default() -> Self {
-        Settings {
-            data_url: "https://raw.githubusercontent.com/nazmul-pro/iustadji/data/dars.json".to_string(),
-            interval: 10,
-            dars_start_date: String::new(),
-            dars_end_date: String::new(),
-            mute_for: 0,
-            mute_def: Vec::new(),
-            pick_random: false,
-            skip_ids: Vec::new(),
-        }
-    }
-}
+use thaw::{Button, ButtonColor, Divider, Input, InputVariant, TextArea, TimePicker};
+use thaw::{DatePicker, InputNumber, Select, SelectOption, SignalWatch, Switch};
+
+// display formats offered in settings, chrono strftime patterns
+const DATE_FORMATS: [(&str, &str); 5] = [
+    ("Sat, 16 Mar 2024", date::DEFAULT_DISPLAY_FORMAT),
+    ("16 March 2024", "%-d %B %Y"),
+    ("16.03.2024", "%d.%m.%Y"),
+    ("2024-03-16", "%Y-%m-%d"),
+    ("03/16/2024", "%m/%d/%Y"),
+];
 
 #[derive(Copy, Clone)]
 struct DarsContext(ReadSignal<Vec<Dars>>, WriteSignal<Vec<Dars>>);
 
 #[derive(Copy, Clone)]
-struct AllDarsContext(ReadSignal<Vec<Dars>>, WriteSignal<Vec<Dars>>);
+struct AllDarsContext(ReadSignal<Vec<Dars>>);
 
 #[derive(Copy, Clone)]
 struct SettingsContext(ReadSignal<Settings>, WriteSignal<Settings>);
@@ -87,27 +35,35 @@ pub fn App() -> impl IntoView {
     let (all_dars, set_all_dars) = create_signal(vec![]);
     let (dars, set_dars) = create_signal(vec![]);
     provide_context(DarsContext(dars, set_dars));
-    provide_context(AllDarsContext(all_dars, set_all_dars));
+    provide_context(AllDarsContext(all_dars));
+
+    let (settings, set_settings) = create_signal(Settings::default());
+    provide_context(SettingsContext(settings, set_settings));
 
     let get_data = move || {
         spawn_local(async move {
-            let args = to_value(&DarsArg {
-                date: "10.10.2023".to_string(),
-            })
-            .unwrap();
+            match ipc::get_dars().await {
+                Ok(data) => {
+                    set_dars.set(data.clone());
+                    set_all_dars.set(data);
+                }
+                Err(err) => error!("Failed to load dars: {}", err),
+            }
 
-            let dars_str = invoke("get_dars", args).await.as_string().unwrap();
-            let data: Vec<Dars> = serde_json::from_str(&dars_str).unwrap();
-            set_dars.set(data.clone());
-            set_all_dars.set(data);
+            match ipc::get_settings().await {
+                Ok(data) => set_settings.set(data),
+                Err(err) => error!("Failed to load settings: {}", err),
+            }
         });
-        
+
     };
 
     get_data();
+    ipc::on::<SettingsChanged>(move |settings| set_settings.set(settings));
 
     view! {
         <Router>
+            <LinkHandler/>
             <div class="flex h-screen">
                 <div class="bg-gray-800 text-white w-30">
                     <div class="p-4">
@@ -120,14 +76,8 @@ pub fn App() -> impl IntoView {
                 </div>
                 <div class="flex-grow overflow-auto">
             <Routes>
-                <Route path="/" view=|| view! {
-                    <div class="sticky top-0 bg-gray-100 p-3 text-xs">
-                        <Header/>
-                    </div>
-                    <div class="overflow-auto text-xs">
-                        <DarsList/>
-                    </div>
-                }/>
+                <Route path="/" view=Home/>
+                <Route path="/dars/:date/:id" view=Home/>
                 <Route
                     path="/settings"
                     view=Settings
@@ -140,40 +90,113 @@ pub fn App() -> impl IntoView {
     }
 }
 
+#[component]
+fn Home() -> impl IntoView {
+    view! {
+        <div class="sticky top-0 bg-gray-100 p-3 text-xs">
+            <Header/>
+        </div>
+        <div class="overflow-auto text-xs">
+            <FetchProgress/>
+            <SnoozedList/>
+            <DarsList/>
+        </div>
+    }
+}
+
+// routes the backend asks for, e.g. for an iustadji://dars link
+#[component]
+fn LinkHandler() -> impl IntoView {
+    let navigate = use_navigate();
+    let take_route = move || {
+        let navigate = navigate.clone();
+        spawn_local(async move {
+            match ipc::take_route().await {
+                Ok(Some(path)) => navigate(&path, Default::default()),
+                Ok(None) => {}
+                Err(err) => error!("Failed to take route: {}", err),
+            }
+        });
+    };
+    take_route();
+    ipc::on::<Navigate>(move |()| take_route());
+}
+
 #[component]
 fn Settings() -> impl IntoView {
 
+    let SettingsContext(settings, set_settings) = use_context::<SettingsContext>().unwrap();
+    // today in the timezone of the settings, not the system's
+    let today = settings.with_untracked(|s| s.zone().now().date());
     let data_url = create_rw_signal(String::new());
     let interval = create_rw_signal(1);
+    let snooze_minutes = create_rw_signal(10);
     
-    let sd = NaiveDate::parse_from_str("01.01.2023", "%d.%m.%Y").unwrap();
-    let dars_start_date = create_rw_signal(Some(sd));
-
-    let ed = NaiveDate::parse_from_str("12.12.2025", "%d.%m.%Y").unwrap();
-    let dars_end_date = create_rw_signal(Some(ed));
+    let dars_start_date = create_rw_signal(Some(today));
+    let dars_end_date = create_rw_signal(Some(today));
+    let dars_window = create_rw_signal(Some(DarsWindow::All));
+    let window_options = vec![
+        SelectOption { label: "All".to_string(), value: DarsWindow::All },
+        SelectOption { label: "Last 7 days".to_string(), value: DarsWindow::Last7Days },
+        SelectOption { label: "Last 30 days".to_string(), value: DarsWindow::Last30Days },
+        SelectOption { label: "This Hijri month".to_string(), value: DarsWindow::ThisHijriMonth },
+        SelectOption { label: "Between dates".to_string(), value: DarsWindow::Custom },
+    ];
 
-    let mute_for = create_rw_signal(0);
     let pick_random = create_rw_signal(false);
+    let show_hijri = create_rw_signal(false);
+    let date_format = create_rw_signal(Some(date::DEFAULT_DISPLAY_FORMAT.to_string()));
+    let date_format_options = DATE_FORMATS
+        .iter()
+        .map(|(label, format)| SelectOption { label: label.to_string(), value: format.to_string() })
+        .collect::<Vec<_>>();
+    let anchors = create_rw_signal(Vec::<(String, RwSignal<Option<NaiveTime>>)>::new());
+    let sources = create_rw_signal(Vec::<Source>::new());
+    // url of the source whose options are open
+    let editing = create_rw_signal(None::<String>);
+    // "" stands for the system timezone
+    let timezone = create_rw_signal(Some(String::new()));
+    let timezone_options = std::iter::once(SelectOption { label: "System".to_string(), value: String::new() })
+        .chain(Zone::names().map(|name| SelectOption { label: name.to_string(), value: name.to_string() }))
+        .collect::<Vec<_>>();
+    let mute_date = create_rw_signal(Some(today));
+    let mute_time = create_rw_signal(NaiveTime::from_hms_opt(7, 0, 0));
     
-    let (settings, set_settings) = create_signal(Settings::default());
-    provide_context(SettingsContext(settings, set_settings));
+    // an entry for the data location only holds its options
+    let other_sources = move || {
+        let data_url = settings.with(|s| s.data_url.clone());
+        sources.get().into_iter().filter(|s| s.url != data_url).collect::<Vec<_>>()
+    };
+    // follows the context, sources can be added by a subscribe link while this page is open
+    create_effect(move |_| sources.set(settings.with(|s| s.sources.clone())));
     spawn_local(async move {
-        let args = to_value(&DarsArg {
-            date: "10.10.2023".to_string(),
-        })
-        .unwrap();
-        let sett_str = invoke("get_settings_str", args).await.as_string().unwrap();
-        let data: Settings = serde_json::from_str(&sett_str).unwrap();
-        set_settings.set(data.clone());
+        match ipc::get_settings().await {
+            Ok(data) => set_settings.set(data),
+            Err(err) => error!("Failed to load settings: {}", err),
+        }
 
         data_url.set(settings.get().data_url);
         interval.set(settings.get().interval);
-        let sd = NaiveDate::parse_from_str(&settings.get().dars_start_date, "%d.%m.%Y").unwrap();
-        dars_start_date.set(Some(sd));
-        let ed = NaiveDate::parse_from_str(&settings.get().dars_end_date, "%d.%m.%Y").unwrap();
-        dars_end_date.set(Some(ed));
+        snooze_minutes.set(settings.get().snooze_minutes);
+        dars_start_date.set(Some(settings.get().dars_start_date.naive()));
+        dars_end_date.set(Some(settings.get().dars_end_date.naive()));
+        dars_window.set(Some(settings.get().dars_window));
         log!("{}", settings.get().pick_random);
         pick_random.set(settings.get().pick_random);
+        show_hijri.set(settings.get().show_hijri);
+        date_format.set(Some(settings.get().date_format));
+        timezone.set(Some(settings.get().timezone.unwrap_or_default()));
+        anchors.set(
+            settings
+                .get()
+                .anchors
+                .into_iter()
+                .map(|a| {
+                    let time = NaiveTime::parse_from_str(&a.time, "%H:%M").ok();
+                    (a.name, create_rw_signal(time))
+                })
+                .collect(),
+        );
     });
 
     view! {
@@ -187,30 +210,115 @@ fn Settings() -> impl IntoView {
                 <div class="flex items-center gap-2.5 mb-5">
                     <div>Data location</div>
                     <div class="flex-1"><Input value=data_url/></div>
+                    <Button on:click=move |_| editing.set(Some(settings.with(|s| s.data_url.clone())))>Options</Button>
                 </div>
+                <Show when=move || editing.get().is_some_and(|url| settings.with(|s| s.data_url == url))>
+                    <SourceOptions url=settings.with_untracked(|s| s.data_url.clone()) editing/>
+                </Show>
                 <div class="flex items-center gap-2.5 mb-5">
                     <div>"Notification interval"</div>
                     <div><InputNumber value=interval step=5/></div> min
                 </div>
+                <div class="flex items-center gap-2.5 mb-5">
+                    <div>"Snooze for"</div>
+                    <div><InputNumber value=snooze_minutes step=5/></div> min
+                </div>
                 <div class="items-center gap-2.5 mb-5">
-                    <p class="font-bold mb-2">Notify dars between:</p>
-                    <div class="flex mb-2">
-                        <p class="pr-2 pt-2">Start date</p>
-                        <DatePicker value=dars_start_date/>
-                    </div>
-                    <div class="flex">
-                        <p class="pr-2 pt-2">End date</p>
-                        <DatePicker value=dars_end_date/>
+                    <p class="font-bold mb-2">Notify dars from:</p>
+                    <div class="flex mb-2 w-60">
+                        <Select value=dars_window options=window_options/>
                     </div>
+                    <Show when=move || dars_window.get() == Some(DarsWindow::Custom)>
+                        <div class="flex mb-2">
+                            <p class="pr-2 pt-2">Start date</p>
+                            <DatePicker value=dars_start_date/>
+                        </div>
+                        <div class="flex">
+                            <p class="pr-2 pt-2">End date</p>
+                            <DatePicker value=dars_end_date/>
+                        </div>
+                    </Show>
                 </div>
                 <div class="flex items-center gap-2.5 mb-5">
                     <div>Notify random</div>
                     <div><Switch value=pick_random /></div>
                 </div>
-                // <div class="flex items-center gap-2.5 mb-5">
-                //     <div>Mute for next</div>
-                //     <div><InputNumber value=mute_for step=5/></div> min
-                // </div>
+                <div class="flex items-center gap-2.5 mb-5">
+                    <div>Date format</div>
+                    <div class="w-48"><Select value=date_format options=date_format_options/></div>
+                </div>
+                <div class="flex items-center gap-2.5 mb-5">
+                    <div>Timezone</div>
+                    <div class="w-60"><Select value=timezone options=timezone_options/></div>
+                </div>
+                <div class="flex items-center gap-2.5 mb-5">
+                    <div>Show Hijri dates</div>
+                    <div><Switch value=show_hijri /></div>
+                </div>
+                <div class="font-bold">Other sources</div>
+                <Divider class="m-2"/>
+                <For
+                    each=other_sources
+                    key=|source| source.url.clone()
+                    let:source
+                >
+                    {
+                        let url = source.url.clone();
+                        let open = source.url.clone();
+                        let shown = source.url.clone();
+                        view! {
+                            <div class="flex items-center gap-2.5 mb-5">
+                                <div class="flex-1 truncate">{source.url.clone()}</div>
+                                <Button on:click=move |_| editing.set(Some(open.clone()))>Options</Button>
+                                <Button on:click=move |_| {
+                                    sources.update(|sources| sources.retain(|s| s.url != source.url));
+                                } color=ButtonColor::Error>Remove</Button>
+                            </div>
+                            <Show when=move || editing.get().as_ref() == Some(&shown)>
+                                <SourceOptions url=url.clone() editing/>
+                            </Show>
+                        }
+                    }
+                </For>
+                <div class="font-bold">Prayer times</div>
+                <Divider class="m-2"/>
+                <For
+                    each=move || anchors.get()
+                    key=|anchor| anchor.0.clone()
+                    let:anchor
+                >
+                    <div class="flex items-center gap-2.5 mb-5">
+                        <div class="w-20 capitalize">{anchor.0}</div>
+                        <div><TimePicker value=anchor.1/></div>
+                    </div>
+                </For>
+                <div class="font-bold">"Mute until…"</div>
+                <Divider class="m-2"/>
+                <div class="flex items-center gap-2.5 mb-5">
+                    <div><DatePicker value=mute_date/></div>
+                    <div><TimePicker value=mute_time/></div>
+                    <Button on:click=move |_| {
+                        let until = mute_date
+                            .get()
+                            .zip(mute_time.get())
+                            .and_then(|(date, time)| settings.with_untracked(|s| s.zone()).resolve(date.and_time(time)))
+                            .filter(|until| *until > Utc::now());
+                        match until {
+                            Some(until) => set_mute(Some(until)),
+                            None => show_toast(ToastOptions {
+                                message: "Pick a time in the future".to_string(),
+                                duration: Duration::from_millis(3000),
+                            }),
+                        }
+                    }>Mute</Button>
+                    <Show when=move || settings.with(|s| s.is_muted(Utc::now()))>
+                        <Button on:click=move |_| set_mute(None) color=ButtonColor::Error>Unmute</Button>
+                    </Show>
+                </div>
+                <div class="mb-5">{move || settings.with(|s| match s.active_mute(Utc::now()) {
+                    Some(until) => format!("Muted until {}", s.zone().local(until).format("%d.%m.%Y %H:%M")),
+                    None => "Not muted".to_string(),
+                })}</div>
                 <div class="font-bold">Mute daily *(TBD)*</div>
                 <Divider class="m-2"/>
                 <div class="flex items-center gap-2.5 mb-5">
@@ -225,34 +333,61 @@ fn Settings() -> impl IntoView {
             </div>
 
             <Button on:click=move |_| {
-                if data_url.get().is_empty() || 
-                    interval.get() < 1 || 
-                    dars_start_date.get().is_none() || 
-                    dars_end_date.get().is_none() {
+                if dars_start_date.get().is_none() ||
+                    dars_end_date.get().is_none() ||
+                    dars_window.get().is_none() ||
+                    date_format.get().is_none() ||
+                    anchors.get().iter().any(|a| a.1.get().is_none()) {
                         show_toast(ToastOptions {
-                            message: format!("{}", "Invalid settings"),
+                            message: "Invalid settings".to_string(),
                             duration: Duration::from_millis(3000),
                         });
                         return;
                 }
-                let new_settings: Settings = Settings {
-                    data_url: data_url.get(),
+                let mut new_settings: Settings = Settings {
+                    data_url: settings.get().data_url,
+                    sources: sources.get(),
                     interval: interval.get(),
-                    dars_start_date: dars_start_date.get().unwrap().format("%d.%m.%Y").to_string(),
-                    dars_end_date: dars_end_date.get().unwrap().format("%d.%m.%Y").to_string(),
+                    snooze_minutes: snooze_minutes.get(),
+                    dars_start_date: dars_start_date.get().unwrap().into(),
+                    dars_end_date: dars_end_date.get().unwrap().into(),
+                    dars_window: dars_window.get().unwrap(),
                     pick_random: pick_random.get(),
-                    mute_for: settings.get().mute_for,
+                    muted_until: settings.get().muted_until,
+                    mute_preset: settings.get().mute_preset,
                     mute_def: settings.get().mute_def,
                     skip_ids: settings.get().skip_ids,
+                    learned_ids: settings.get().learned_ids,
+                    show_hijri: show_hijri.get(),
+                    date_format: date_format.get().unwrap(),
+                    timezone: timezone.get().filter(|name| !name.is_empty()),
+                    anchors: anchors
+                        .get()
+                        .into_iter()
+                        .map(|(name, time)| AnchorDef {
+                            name,
+                            time: time.get().unwrap().format("%H:%M").to_string(),
+                        })
+                        .collect(),
                 };
+                new_settings.set_data_url(data_url.get());
+                if let Err(err) = new_settings.validate() {
+                    show_toast(ToastOptions {
+                        message: err,
+                        duration: Duration::from_millis(3000),
+                    });
+                    return;
+                }
                 spawn_local(async move {
-                    let args = to_value(&SettingsArg {
-                        data: serde_json::to_string_pretty(&new_settings).expect("msg")
-                    })
-                    .unwrap();
-                    let msg = invoke("set_settings_str", args).await.as_string().unwrap();
+                    let msg = match ipc::set_settings(new_settings.clone()).await {
+                        Ok(()) => {
+                            set_settings.set(new_settings);
+                            "Settings successfully updated".to_string()
+                        }
+                        Err(err) => err,
+                    };
                     show_toast(ToastOptions {
-                        message: format!("{}", msg),
+                        message: msg,
                         duration: Duration::from_millis(3000),
                     });
                 });
@@ -262,10 +397,24 @@ fn Settings() -> impl IntoView {
     }
 }
 
+// the backend answers with a SettingsChanged event
+fn set_mute(until: Option<chrono::DateTime<Utc>>) {
+    spawn_local(async move {
+        if let Err(err) = ipc::set_mute(until).await {
+            show_toast(ToastOptions {
+                message: err,
+                duration: Duration::from_millis(3000),
+            });
+        }
+    });
+}
+
 #[component]
 fn Header() -> impl IntoView {
-    let start = create_rw_signal(Some(Local::now().date_naive()));
-    let end = create_rw_signal(Some(Local::now().date_naive()));
+    let SettingsContext(settings, _) = use_context::<SettingsContext>().unwrap();
+    let today = settings.with_untracked(|s| s.zone().now().date());
+    let start = create_rw_signal(Some(today));
+    let end = create_rw_signal(Some(today));
     let _ = start.watch(move |_| {
         filter_dars(start.get().unwrap(), end.get().unwrap());
     });
@@ -288,38 +437,381 @@ fn Header() -> impl IntoView {
     }
 }
 
+// proxy, login, headers and signing key of one source, saved right away; tokens, passwords and header values
+// go to the system keyring, the rest to the settings
+#[component]
+fn SourceOptions(url: String, editing: RwSignal<Option<String>>) -> impl IntoView {
+    let SettingsContext(settings, set_settings) = use_context::<SettingsContext>().unwrap();
+    let source = settings
+        .with_untracked(|s| s.all_sources().into_iter().find(|s| s.url == url))
+        .unwrap_or_else(|| Source::new(&url));
+
+    let proxy = create_rw_signal(source.proxy.clone().unwrap_or_default());
+    let proxy_password = create_rw_signal(String::new());
+    let public_key = create_rw_signal(source.public_key.clone().unwrap_or_default());
+    let auth = create_rw_signal(Some(match source.auth {
+        SourceAuth::None => "none",
+        SourceAuth::Bearer => "bearer",
+        SourceAuth::Basic { .. } => "basic",
+    }.to_string()));
+    let auth_options = vec![
+        SelectOption { label: "None".to_string(), value: "none".to_string() },
+        SelectOption { label: "Bearer token".to_string(), value: "bearer".to_string() },
+        SelectOption { label: "Username and password".to_string(), value: "basic".to_string() },
+    ];
+    let username = create_rw_signal(match &source.auth {
+        SourceAuth::Basic { username } => username.clone(),
+        _ => String::new(),
+    });
+    // the token or the password, depending on auth
+    let secret = create_rw_signal(String::new());
+    // "Name: value" per line
+    let headers = create_rw_signal(String::new());
+
+    spawn_local({
+        let url = url.clone();
+        async move {
+            match ipc::get_source_secrets(url).await {
+                Ok(stored) => {
+                    let stored_secret = match source.auth {
+                        SourceAuth::None => None,
+                        SourceAuth::Bearer => stored.token,
+                        SourceAuth::Basic { .. } => stored.password,
+                    };
+                    secret.set(stored_secret.unwrap_or_default());
+                    proxy_password.set(stored.proxy_password.unwrap_or_default());
+                    headers.set(
+                        source
+                            .headers
+                            .iter()
+                            .map(|name| format!("{}: {}", name, stored.headers.get(name).cloned().unwrap_or_default()))
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                    );
+                }
+                Err(err) => error!("Failed to load secrets: {}", err),
+            }
+        }
+    });
+
+    let toast = |message: String| show_toast(ToastOptions {
+        message,
+        duration: Duration::from_millis(3000),
+    });
+    let save = move |_| {
+        let mut names = vec![];
+        let mut values = BTreeMap::new();
+        for line in headers.get().lines().filter(|line| !line.trim().is_empty()) {
+            let Some((name, value)) = line.split_once(':') else {
+                toast(format!("Expected \"Name: value\", got {}", line));
+                return;
+            };
+            names.push(name.trim().to_string());
+            values.insert(name.trim().to_string(), value.trim().to_string());
+        }
+        let auth = match auth.get().as_deref() {
+            Some("bearer") => SourceAuth::Bearer,
+            Some("basic") => SourceAuth::Basic { username: username.get().trim().to_string() },
+            _ => SourceAuth::None,
+        };
+        let filled = |value: String| Some(value).filter(|value| !value.is_empty());
+        let secrets = SourceSecrets {
+            token: filled(secret.get()).filter(|_| auth == SourceAuth::Bearer),
+            password: filled(secret.get()).filter(|_| matches!(auth, SourceAuth::Basic { .. })),
+            proxy_password: filled(proxy_password.get()),
+            headers: values,
+        };
+        let source = Source {
+            url: url.clone(),
+            proxy: filled(proxy.get().trim().to_string()),
+            auth,
+            headers: names,
+            public_key: filled(public_key.get().trim().to_string()),
+        };
+        if let Err(err) = source.validate() {
+            toast(err);
+            return;
+        }
+
+        let mut new_settings = settings.get_untracked();
+        match new_settings.sources.iter_mut().find(|s| s.url == source.url) {
+            Some(existing) => *existing = source,
+            None => new_settings.sources.push(source),
+        }
+        let url = url.clone();
+        spawn_local(async move {
+            let saved = match ipc::set_source_secrets(url, secrets).await {
+                Ok(()) => ipc::set_settings(new_settings.clone()).await,
+                Err(err) => Err(err),
+            };
+            match saved {
+                Ok(()) => {
+                    set_settings.set(new_settings);
+                    editing.set(None);
+                }
+                Err(err) => toast(err),
+            }
+        });
+    };
+
+    view! {
+        <div class="ml-5 mb-5 flex-col">
+            <div class="flex items-center gap-2.5 mb-2">
+                <div class="w-28">Proxy</div>
+                <div class="flex-1"><Input value=proxy placeholder="socks5://127.0.0.1:1080"/></div>
+            </div>
+            <div class="flex items-center gap-2.5 mb-2">
+                <div class="w-28">Proxy password</div>
+                <div class="flex-1"><Input value=proxy_password variant=InputVariant::Password/></div>
+            </div>
+            <div class="flex items-center gap-2.5 mb-2">
+                <div class="w-28">Login</div>
+                <div class="w-60"><Select value=auth options=auth_options/></div>
+            </div>
+            <Show when=move || auth.get().as_deref() == Some("basic")>
+                <div class="flex items-center gap-2.5 mb-2">
+                    <div class="w-28">Username</div>
+                    <div class="flex-1"><Input value=username/></div>
+                </div>
+            </Show>
+            <Show when=move || auth.get().is_some_and(|auth| auth != "none")>
+                <div class="flex items-center gap-2.5 mb-2">
+                    <div class="w-28">{move || if auth.get().as_deref() == Some("basic") { "Password" } else { "Token" }}</div>
+                    <div class="flex-1"><Input value=secret variant=InputVariant::Password/></div>
+                </div>
+            </Show>
+            <div class="flex gap-2.5 mb-2">
+                <div class="w-28">Headers</div>
+                <div class="flex-1"><TextArea value=headers placeholder="X-Api-Key: …"/></div>
+            </div>
+            <div class="flex items-center gap-2.5 mb-2">
+                <div class="w-28">Public key</div>
+                <div class="flex-1"><Input value=public_key placeholder="Ed25519 key in base64, the feed must be signed in <url>.sig"/></div>
+            </div>
+            <div class="flex gap-2.5">
+                <Button on:click=save>Save</Button>
+                <Button on:click=move |_| editing.set(None)>Cancel</Button>
+            </div>
+        </div>
+    }
+}
+
+// the source being loaded, hidden again once every source answered; a feed rejected for its
+// signature stays listed until it loads fine
+#[component]
+fn FetchProgress() -> impl IntoView {
+    let status = create_rw_signal(None::<FetchStatus>);
+    let rejected = create_rw_signal(Vec::<(String, String)>::new());
+    let apply = move |data: FetchStatus| {
+        match &data.state {
+            FetchState::Rejected { error } => rejected.update(|r| {
+                r.retain(|(url, _)| *url != data.url);
+                r.push((data.url.clone(), error.clone()));
+            }),
+            FetchState::Done { .. } => rejected.update(|r| r.retain(|(url, _)| *url != data.url)),
+            _ => {}
+        }
+        status.set(Some(data));
+    };
+    // what was reported before this listened, e.g. a feed rejected during the fetch at launch
+    spawn_local(async move {
+        match ipc::get_fetch_status().await {
+            Ok(all) => all.into_iter().for_each(apply),
+            Err(err) => error!("Failed to load the fetch status: {}", err),
+        }
+    });
+    ipc::on::<FetchStatusChanged>(apply);
+
+    view! {
+        {move || status.get().and_then(|FetchStatus { url, state }| {
+            let (text, running) = match state {
+                FetchState::Fetching { attempt: 1, .. } => (format!("Loading {}", url), true),
+                FetchState::Fetching { attempt, tries } => (format!("Loading {} (try {} of {})", url, attempt, tries), true),
+                FetchState::Retrying { error, seconds } => (format!("{}, retrying in {}s", error, seconds), true),
+                FetchState::Failed { error } => (format!("Failed to load {}: {}", url, error), false),
+                FetchState::Done { .. } | FetchState::Rejected { .. } | FetchState::Cancelled => return None,
+            };
+            Some(view! {
+                <div class="m-2 p-3 bg-white border border-gray-200 rounded-lg shadow flex items-center gap-2.5">
+                    <div class="flex-1 truncate">{text}</div>
+                    <Show when=move || running>
+                        <Button on:click=move |_| {
+                            spawn_local(async move {
+                                if let Err(err) = ipc::cancel_fetch().await {
+                                    error!("{}", err);
+                                }
+                            });
+                        } color=ButtonColor::Error>"Cancel"</Button>
+                    </Show>
+                </div>
+            })
+        })}
+        <For
+            each=move || rejected.get()
+            key=|(url, _)| url.clone()
+            let:entry
+        >
+            <div class="m-2 p-3 bg-red-50 border border-red-300 rounded-lg shadow text-red-700">
+                <p class="font-bold">{format!("Rejected {}", entry.0)}</p>
+                <p>{entry.1}</p>
+            </div>
+        </For>
+    }
+}
+
+// reminders put back from a card or a notification, until they come due again
+#[component]
+fn SnoozedList() -> impl IntoView {
+    let SettingsContext(settings, _) = use_context::<SettingsContext>().unwrap();
+    let snoozed = create_rw_signal(Vec::<Snoozed>::new());
+    spawn_local(async move {
+        match ipc::get_snoozed().await {
+            Ok(data) => snoozed.set(data),
+            Err(err) => error!("Failed to load snoozed reminders: {}", err),
+        }
+    });
+    ipc::on::<SnoozedChanged>(move |data| snoozed.set(data));
+
+    view! {
+        <Show when=move || snoozed.with(|s| !s.is_empty())>
+            <div class="m-2 p-3 bg-white border border-gray-200 rounded-lg shadow">
+                <p class="font-bold mb-2">"Snoozed"</p>
+                <For
+                    each=move || snoozed.get()
+                    key=|s| (s.notification.id.clone(), s.at)
+                    let:entry
+                >
+                    {
+                        let id = entry.notification.id.clone();
+                        let link = split_id(&id).map(|(date, id)| link::dars_path(date, id));
+                        view! {
+                            <div class="flex items-center gap-2.5 mb-1">
+                                <span class="rounded-2xl bg-gray-800 text-white px-2">{move || settings.with(|s| s.zone().local(entry.at).format("%H:%M").to_string())}</span>
+                                <a class="flex-1 truncate" href=link>{entry.notification.title.clone()}</a>
+                                <Button on:click=move |_| {
+                                    let id = id.clone();
+                                    spawn_local(async move {
+                                        if let Err(err) = ipc::cancel_snooze(id).await {
+                                            error!("{}", err);
+                                        }
+                                    });
+                                } color=ButtonColor::Error>"Cancel"</Button>
+                            </div>
+                        }
+                    }
+                </For>
+            </div>
+        </Show>
+    }
+}
+
 #[component]
 fn DarsList() -> impl IntoView {
-    let dars = use_context::<DarsContext>().unwrap().0;
+    let DarsContext(dars, set_dars) = use_context::<DarsContext>().unwrap();
+    let all_dars = use_context::<AllDarsContext>().unwrap().0;
+    let settings = use_context::<SettingsContext>().unwrap().0;
+
+    // the card opened through /dars/:date/:id
+    let params = use_params_map();
+    let target = move || {
+        params.with(|p| Some((DarsDate::parse(p.get("date")?)?, p.get("id")?.clone())))
+    };
+    create_effect(move |_| {
+        let Some((date, id)) = target() else {
+            return;
+        };
+        // the date filter of the header may hide it
+        let shown = dars.with_untracked(|dars| dars.iter().any(|d| d.date == date));
+        if !shown && all_dars.with(|all| all.iter().any(|d| d.date == date)) {
+            set_dars.set(all_dars.get_untracked());
+        }
+        request_animation_frame(move || {
+            if let Some(card) = document().get_element_by_id(&card_id(date, &id)) {
+                card.scroll_into_view();
+            }
+        });
+    });
 
     view! {
         <For
             each= move || dars.get()
-            key=|state| state.date.clone()
+            key=|state| state.date
             let:child
         >
-            <div class="flex justify-center"><p class="border rounded-2xl font-bold text-center m-4 p-1 bg-green-600 text-white w-40">{format_date(&child.date)}</p></div>
+            <div class="flex justify-center"><p class="border rounded-2xl font-bold text-center m-4 p-1 px-4 bg-green-600 text-white min-w-40">{
+                let date = child.date;
+                move || settings.with(|settings| format_date(date, settings))
+            }</p></div>
             <For
-                each= move || child.notifications.clone()
-                key=|state| state.id.clone()
-                let:child
+                each= move || {
+                    let date = child.date;
+                    child.notifications.clone().into_iter().map(move |n| (date, n)).collect::<Vec<_>>()
+                }
+                key=|state| state.1.id.clone()
+                let:entry
             >
-
-                <div href="#" class="block p-6 m-2 bg-white border border-gray-200 rounded-lg shadow hover:bg-gray-100">
-                    <h6 class="mb-2 font-bold tracking-tight text-gray-900">{child.title}</h6>
-                    <p class="font-normal text-gray-700 dark:text-gray-700">{child.description}</p>
-                </div>
-
+                {
+                    let (date, child) = entry;
+                    let id = child.id.clone();
+                    let highlighted = move || target() == Some((date, id.clone()));
+                    // the backend knows reminders by their composite id
+                    let queued = NotificationData { id: composite_id(date, &child.id), ..child.clone() };
+                    view! {
+                        <div href="#"
+                            id=card_id(date, &child.id)
+                            class=move || if highlighted() {
+                                "block p-6 m-2 bg-white border-2 border-green-600 rounded-lg shadow hover:bg-gray-100"
+                            } else {
+                                "block p-6 m-2 bg-white border border-gray-200 rounded-lg shadow hover:bg-gray-100"
+                            }
+                        >
+                            {
+                                let composite = composite_id(date, &child.id);
+                                move || settings.with(|s| s.learned_ids.contains(&composite)).then(|| view! {
+                                    <span class="float-right rounded-2xl bg-blue-600 text-white px-2 ml-1">"Learned"</span>
+                                })
+                            }
+                            {child.time.map(|time| view! {
+                                <span class="float-right rounded-2xl bg-gray-800 text-white px-2 capitalize">{time}</span>
+                            })}
+                            {child.active.into_iter().map(|rule| view! {
+                                <span class="float-right rounded-2xl bg-green-600 text-white px-2 mr-1 capitalize">{rule.replace('_', " ")}</span>
+                            }).collect_view()}
+                            <h6 class="mb-2 font-bold tracking-tight text-gray-900">{child.title}</h6>
+                            <p class="font-normal text-gray-700 dark:text-gray-700">{child.description}</p>
+                            <Button class="mt-2" on:click=move |_| {
+                                let notification = queued.clone();
+                                let minutes = settings.with_untracked(|s| s.snooze_minutes);
+                                spawn_local(async move {
+                                    let msg = match ipc::snooze(notification, minutes).await {
+                                        Ok(()) => format!("Snoozed for {} min", minutes),
+                                        Err(err) => err,
+                                    };
+                                    show_toast(ToastOptions {
+                                        message: msg,
+                                        duration: Duration::from_millis(3000),
+                                    });
+                                });
+                            }>"Snooze"</Button>
+                        </div>
+                    }
+                }
             </For>
         </For>
     }
 }
 
-fn format_date(date: &str) -> String {
-    let date = NaiveDate::parse_from_str(date, "%d.%m.%Y").expect("Invalid date format");
+// the feed id is encoded, an element id can't hold spaces
+fn card_id(date: DarsDate, id: &str) -> String {
+    format!("dars-{}-{}", date, link::percent_encode(id))
+}
 
-    let formatted_date = date.format("%a, %-d %b %Y").to_string();
-    formatted_date
+fn format_date(date: DarsDate, settings: &Settings) -> String {
+    let formatted_date = date.display(&settings.date_format);
+    match HijriDate::from_gregorian(date.naive()) {
+        Some(hijri) if settings.show_hijri => format!("{} · {}", formatted_date, hijri),
+        _ => formatted_date,
+    }
 }
 
 fn filter_dars(start: NaiveDate, end: NaiveDate) {
@@ -329,10 +821,7 @@ fn filter_dars(start: NaiveDate, end: NaiveDate) {
     let filtered = all_dars
         .get_untracked()
         .iter()
-        .filter(|d| {
-            let dars_date = NaiveDate::parse_from_str(&d.date, "%d.%m.%Y").unwrap();
-            dars_date >= start && dars_date <= end
-        })
+        .filter(|d| d.date.naive() >= start && d.date.naive() <= end)
         .cloned()
         .collect::<Vec<_>>();
     set_dars.set(filtered);
@@ -0,0 +1,122 @@
+use iustadji_domain::ipc::{self, Command, Event};
+use chrono::{DateTime, Utc};
+use iustadji_domain::ipc::{FetchStatus, Snoozed};
+use iustadji_domain::{Dars, NotificationData, Settings, SourceSecrets};
+use leptos::logging::error;
+use leptos::on_cleanup;
+use serde::Serialize;
+use serde_wasm_bindgen::{from_value, Serializer};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(catch, js_namespace = ["window", "__TAURI__", "tauri"])]
+    async fn invoke(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(catch, js_namespace = ["window", "__TAURI__", "event"])]
+    async fn listen(event: &str, handler: &Closure<dyn FnMut(JsValue)>) -> Result<JsValue, JsValue>;
+}
+
+// the invoke payload, handlers take the arguments as their `args` parameter
+#[derive(Serialize)]
+struct Payload<'a, A> {
+    args: &'a A,
+}
+
+// invokes the command described by `C`, a rejected promise comes back as its message; maps are
+// sent as plain objects, invoke turns a JS Map into `{}`
+pub async fn call<C: Command>(args: &C::Args) -> Result<C::Output, String> {
+    let args = Payload { args }
+        .serialize(&Serializer::json_compatible())
+        .map_err(|err| err.to_string())?;
+    let output = invoke(C::NAME, args)
+        .await
+        .map_err(|err| err.as_string().unwrap_or_else(|| format!("{:?}", err)))?;
+    from_value(output).map_err(|err| err.to_string())
+}
+
+// calls `handler` with the payload of every `E` until the calling component is cleaned up
+pub fn on<E: Event>(handler: impl Fn(E::Payload) + 'static) {
+    let handler = Closure::<dyn FnMut(JsValue)>::new(move |event: JsValue| {
+        let payload = js_sys::Reflect::get(&event, &JsValue::from_str("payload"))
+            .map_err(|err| format!("{:?}", err))
+            .and_then(|payload| from_value(payload).map_err(|err| err.to_string()));
+        match payload {
+            Ok(payload) => handler(payload),
+            Err(err) => error!("Invalid {} event: {}", E::NAME, err),
+        }
+    });
+    // the unlisten function with the handler it keeps alive, once `listen` resolved
+    let listener = Rc::new(RefCell::new(None::<(js_sys::Function, Closure<dyn FnMut(JsValue)>)>));
+    let disposed = Rc::new(Cell::new(false));
+    on_cleanup({
+        let listener = Rc::clone(&listener);
+        let disposed = Rc::clone(&disposed);
+        move || {
+            disposed.set(true);
+            if let Some((unlisten, _handler)) = listener.take() {
+                let _ = unlisten.call0(&JsValue::NULL);
+            }
+        }
+    });
+    wasm_bindgen_futures::spawn_local(async move {
+        match listen(E::NAME, &handler).await {
+            // cleaned up while `listen` was pending
+            Ok(unlisten) if disposed.get() => {
+                let _ = unlisten.unchecked_into::<js_sys::Function>().call0(&JsValue::NULL);
+            }
+            Ok(unlisten) => *listener.borrow_mut() = Some((unlisten.unchecked_into(), handler)),
+            Err(err) => error!("Failed to listen to {}: {:?}", E::NAME, err),
+        }
+    });
+}
+
+pub async fn get_dars() -> Result<Vec<Dars>, String> {
+    call::<ipc::GetDars>(&()).await
+}
+
+pub async fn cancel_fetch() -> Result<(), String> {
+    call::<ipc::CancelFetch>(&()).await
+}
+
+pub async fn get_fetch_status() -> Result<Vec<FetchStatus>, String> {
+    call::<ipc::GetFetchStatus>(&()).await
+}
+
+pub async fn get_settings() -> Result<Settings, String> {
+    call::<ipc::GetSettings>(&()).await
+}
+
+pub async fn set_settings(settings: Settings) -> Result<(), String> {
+    call::<ipc::SetSettings>(&ipc::SetSettingsArgs { settings }).await
+}
+
+pub async fn set_mute(until: Option<DateTime<Utc>>) -> Result<(), String> {
+    call::<ipc::SetMute>(&ipc::SetMuteArgs { until }).await
+}
+
+pub async fn get_source_secrets(url: String) -> Result<SourceSecrets, String> {
+    call::<ipc::GetSourceSecrets>(&ipc::GetSourceSecretsArgs { url }).await
+}
+
+pub async fn set_source_secrets(url: String, secrets: SourceSecrets) -> Result<(), String> {
+    call::<ipc::SetSourceSecrets>(&ipc::SetSourceSecretsArgs { url, secrets }).await
+}
+
+pub async fn get_snoozed() -> Result<Vec<Snoozed>, String> {
+    call::<ipc::GetSnoozed>(&()).await
+}
+
+pub async fn snooze(notification: NotificationData, minutes: u64) -> Result<(), String> {
+    call::<ipc::Snooze>(&ipc::SnoozeArgs { notification, minutes }).await
+}
+
+pub async fn cancel_snooze(id: String) -> Result<(), String> {
+    call::<ipc::CancelSnooze>(&ipc::CancelSnoozeArgs { id }).await
+}
+
+pub async fn take_route() -> Result<Option<String>, String> {
+    call::<ipc::TakeRoute>(&()).await
+}
@@ -1,4 +1,5 @@
 mod app;
+mod ipc;
 
 use app::*;
 use leptos::*;